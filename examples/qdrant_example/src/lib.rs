@@ -7,4 +7,8 @@ impl vectorctl::MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn vectorctl::MigrationTrait>> {
         vec![Box::new(version_20250522_145910_init_migration::Migration)]
     }
+
+    fn version() -> Option<&'static str> {
+        Some(env!("CARGO_PKG_VERSION"))
+    }
 }
@@ -1,12 +1,11 @@
-use vectorctl::{Cli, Parser, VectorTrait};
+use vectorctl::{Cli, Parser};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    let dataset_url = cli.database_url;
-    let api_key = cli.api_key;
 
-    let context = vectorctl::Context::new(vectorctl::Backend::new(&dataset_url, api_key)?);
+    let context = vectorctl::Context::from_url(&cli.database_url, cli.api_key.clone())?
+        .with_ledger(cli.ledger_options());
 
     vectorctl::run_migrate(qdrant_exemple::Migrator, &context)
         .await
@@ -5,7 +5,7 @@ use vectorctl::{
 
 pub const REVISION: Revision = Revision {
     date: "2025-05-22T14:59:10",
-    down_revision: None,
+    down_revision: &[],
     revision: "t1Jy_CxeQoU",
     message: None,
 };
@@ -16,7 +16,7 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, ctx: &vectorctl::Context) -> Result<(), MigrationError> {
-        let qdrant = &ctx.backend.client;
+        let qdrant = ctx.backend.qdrant()?;
 
         qdrant
             .create_collection(
@@ -29,7 +29,7 @@ impl MigrationTrait for Migration {
         Ok(())
     }
     async fn down(&self, ctx: &vectorctl::Context) -> Result<(), MigrationError> {
-        let qdrant = &ctx.backend.client;
+        let qdrant = ctx.backend.qdrant()?;
 
         qdrant
             .delete_collection("my_collection")
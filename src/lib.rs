@@ -4,5 +4,5 @@ pub use vectorctl_migration::*;
 #[cfg(feature = "macros")]
 pub use vectorctl_macros::*;
 
-#[cfg(feature = "qdrant-backend")]
+#[cfg(any(feature = "qdrant-backend", feature = "in-memory-backend"))]
 pub use vectorctl_backend::generic::{LedgerTrait, VectorBackendError, VectorTrait};
@@ -0,0 +1,216 @@
+use crate::generic::{
+    HistoryEntry, LedgerEntry, LedgerOptions, LedgerRecord, LedgerTrait, VectorBackendError,
+    VectorTrait,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+#[cfg(feature = "in-memory-backend")]
+use crate::memory::{self, MemoryBackend, MemoryClient};
+#[cfg(feature = "qdrant-backend")]
+use crate::qdrant::{self, QdrantBackend};
+
+/// One of the backends compiled into this build, picked at runtime from the
+/// database URL scheme.
+#[derive(Clone)]
+pub enum AnyBackend {
+    #[cfg(feature = "qdrant-backend")]
+    Qdrant(QdrantBackend),
+    #[cfg(feature = "in-memory-backend")]
+    Memory(MemoryBackend),
+}
+
+/// URL schemes understood by [`AnyBackend::new`] in this build.
+pub const SCHEMES: &[&str] = &[
+    #[cfg(feature = "qdrant-backend")]
+    "http",
+    #[cfg(feature = "qdrant-backend")]
+    "https",
+    #[cfg(feature = "in-memory-backend")]
+    "memory",
+];
+
+impl AnyBackend {
+    /// Connects to the backend matching the scheme of `uri`: `http`/`https`
+    /// for Qdrant, `memory` for a fresh in-memory store.
+    pub fn new(uri: &str, api_key: Option<String>) -> Result<Self, VectorBackendError> {
+        let scheme = uri
+            .split_once("://")
+            .map(|(scheme, _)| scheme.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match scheme.as_str() {
+            #[cfg(feature = "qdrant-backend")]
+            "http" | "https" => Ok(Self::Qdrant(<QdrantBackend as VectorTrait>::new(
+                uri, api_key,
+            )?)),
+            #[cfg(feature = "in-memory-backend")]
+            "memory" => Ok(Self::Memory(<MemoryBackend as VectorTrait>::new(
+                uri, api_key,
+            )?)),
+            _ => Err(VectorBackendError::UnsupportedScheme {
+                scheme,
+                supported: SCHEMES.join(", "),
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "qdrant-backend")]
+            Self::Qdrant(_) => "qdrant",
+            #[cfg(feature = "in-memory-backend")]
+            Self::Memory(_) => "memory",
+        }
+    }
+
+    pub fn ledger(&self, options: &LedgerOptions) -> AnyLedger {
+        match self {
+            #[cfg(feature = "qdrant-backend")]
+            Self::Qdrant(backend) => AnyLedger::Qdrant(backend.ledger(options)),
+            #[cfg(feature = "in-memory-backend")]
+            Self::Memory(backend) => AnyLedger::Memory(backend.ledger(options)),
+        }
+    }
+
+    /// The Qdrant client, for migrations written against Qdrant.
+    #[cfg(feature = "qdrant-backend")]
+    pub fn qdrant(&self) -> Result<&Arc<qdrant_client::Qdrant>, VectorBackendError> {
+        match self {
+            Self::Qdrant(backend) => Ok(&backend.client),
+            #[allow(unreachable_patterns)]
+            other => Err(VectorBackendError::Mismatch {
+                expected: "qdrant",
+                actual: other.name(),
+            }),
+        }
+    }
+
+    /// The in-memory store, for migrations exercised in tests.
+    #[cfg(feature = "in-memory-backend")]
+    pub fn memory(&self) -> Result<&Arc<MemoryClient>, VectorBackendError> {
+        match self {
+            Self::Memory(backend) => Ok(&backend.client),
+            #[allow(unreachable_patterns)]
+            other => Err(VectorBackendError::Mismatch {
+                expected: "memory",
+                actual: other.name(),
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "qdrant-backend")]
+impl From<QdrantBackend> for AnyBackend {
+    fn from(backend: QdrantBackend) -> Self {
+        Self::Qdrant(backend)
+    }
+}
+
+#[cfg(feature = "in-memory-backend")]
+impl From<MemoryBackend> for AnyBackend {
+    fn from(backend: MemoryBackend) -> Self {
+        Self::Memory(backend)
+    }
+}
+
+/// The ledger of an [`AnyBackend`].
+#[derive(Clone)]
+pub enum AnyLedger {
+    #[cfg(feature = "qdrant-backend")]
+    Qdrant(qdrant::Ledger),
+    #[cfg(feature = "in-memory-backend")]
+    Memory(memory::Ledger),
+}
+
+macro_rules! dispatch {
+    ($self:ident, $ledger:ident => $call:expr) => {
+        match $self {
+            #[cfg(feature = "qdrant-backend")]
+            AnyLedger::Qdrant($ledger) => $call,
+            #[cfg(feature = "in-memory-backend")]
+            AnyLedger::Memory($ledger) => $call,
+        }
+    };
+}
+
+#[async_trait::async_trait]
+impl LedgerTrait for AnyLedger {
+    type Key = String;
+    type Value = Uuid;
+
+    fn collection_name(&self) -> String {
+        dispatch!(self, ledger => ledger.collection_name())
+    }
+
+    async fn ensure(&self) -> Result<(), VectorBackendError> {
+        dispatch!(self, ledger => ledger.ensure().await)
+    }
+
+    async fn retrieve(
+        &self,
+    ) -> Result<HashMap<Self::Key, LedgerEntry<Self::Value>>, VectorBackendError> {
+        dispatch!(self, ledger => ledger.retrieve().await)
+    }
+
+    async fn history(&self) -> Result<Vec<HistoryEntry<Self::Value>>, VectorBackendError> {
+        dispatch!(self, ledger => ledger.history().await)
+    }
+
+    async fn insert_many(&self, records: Vec<LedgerRecord>) -> Result<(), VectorBackendError> {
+        dispatch!(self, ledger => ledger.insert_many(records).await)
+    }
+
+    async fn delete_many(&self, ids: Vec<Self::Value>) -> Result<(), VectorBackendError> {
+        dispatch!(self, ledger => ledger.delete_many(ids).await)
+    }
+
+    async fn acquire_lock(&self, owner: &str, lease: Duration) -> Result<(), VectorBackendError> {
+        dispatch!(self, ledger => ledger.acquire_lock(owner, lease).await)
+    }
+
+    async fn release_lock(&self, owner: &str) -> Result<(), VectorBackendError> {
+        dispatch!(self, ledger => ledger.release_lock(owner).await)
+    }
+
+    async fn force_unlock(&self) -> Result<(), VectorBackendError> {
+        dispatch!(self, ledger => ledger.force_unlock().await)
+    }
+}
+
+#[cfg(all(test, feature = "in-memory-backend"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheme_selects_backend() {
+        let backend = AnyBackend::new("memory://", None).unwrap();
+        assert_eq!(backend.name(), "memory");
+        assert!(backend.memory().is_ok());
+
+        #[cfg(feature = "qdrant-backend")]
+        {
+            let backend = AnyBackend::new("http://localhost:6334", None).unwrap();
+            assert_eq!(backend.name(), "qdrant");
+            assert!(matches!(
+                backend.memory(),
+                Err(VectorBackendError::Mismatch {
+                    expected: "memory",
+                    actual: "qdrant"
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_unknown_scheme_is_rejected() {
+        assert!(matches!(
+            AnyBackend::new("postgres://localhost", None),
+            Err(VectorBackendError::UnsupportedScheme { scheme, .. }) if scheme == "postgres"
+        ));
+        assert!(matches!(
+            AnyBackend::new("localhost:6334", None),
+            Err(VectorBackendError::UnsupportedScheme { .. })
+        ));
+    }
+}
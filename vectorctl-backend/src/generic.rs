@@ -1,4 +1,6 @@
-use std::{collections::HashMap, sync::Arc};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,6 +13,15 @@ pub enum VectorBackendError {
     Qdrant(Box<qdrant_client::QdrantError>),
     #[error(transparent)]
     Uuid(#[from] uuid::Error),
+    #[error("migration lock is held by {owner} until {expires_at}")]
+    Locked { owner: String, expires_at: String },
+    #[error("unsupported database url scheme `{scheme}`, expected one of: {supported}")]
+    UnsupportedScheme { scheme: String, supported: String },
+    #[error("expected the {expected} backend, got {actual}")]
+    Mismatch {
+        expected: &'static str,
+        actual: &'static str,
+    },
     #[error("Other {0}")]
     Other(String),
 }
@@ -22,6 +33,143 @@ impl From<qdrant_client::QdrantError> for VectorBackendError {
     }
 }
 
+/// Where a ledger lives and which of its records belong to this migrator.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LedgerOptions {
+    /// collection holding the ledger, the backend's default when `None`
+    pub name: Option<String>,
+    /// keeps the records and the lock of independent migrators apart when
+    /// they share one ledger collection
+    pub namespace: Option<String>,
+    /// how long a run holds the migration lock between two renewals, the
+    /// migrator's default when `None`
+    pub lock_lease: Option<Duration>,
+}
+
+/// Version of the ledger record format written by this build. Records without
+/// a version predate it and read as version 0, records before version 2 carry
+/// no event and read as [`LedgerEvent::Up`].
+pub const LEDGER_FORMAT_VERSION: u32 = 2;
+
+/// What happened to a migration. The ledger only ever appends events, rolling
+/// a migration back records a `Down` event next to the `Up` it reverts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEvent {
+    #[default]
+    Up,
+    Down,
+}
+
+/// Audit details stored with a ledger record. Records written by older
+/// versions lack some or all of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub down_revision: Vec<String>,
+    /// time the migration took to run
+    #[serde(
+        default,
+        rename = "duration_ms",
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_millis",
+        deserialize_with = "deserialize_millis"
+    )]
+    pub duration: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vectorctl_version: Option<String>,
+    /// version of the crate the migrator was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrator_version: Option<String>,
+    /// recorded by `stamp` without running the migration
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stamped: bool,
+}
+
+fn serialize_millis<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration
+        .map(|duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+        .serialize(serializer)
+}
+
+fn deserialize_millis<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+}
+
+/// An event to append to the ledger once a migration has been applied or
+/// rolled back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerRecord {
+    pub name: String,
+    pub event: LedgerEvent,
+    pub checksum: Option<String>,
+    pub details: LedgerDetails,
+}
+
+/// An event as read back from the ledger history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry<V> {
+    pub id: V,
+    pub name: String,
+    pub event: LedgerEvent,
+    pub checksum: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+    /// [`LEDGER_FORMAT_VERSION`] of the record when it was written
+    pub format_version: u32,
+    pub details: LedgerDetails,
+}
+
+/// Replays `history`, oldest event first, into the migrations it leaves
+/// applied: those whose last event is an `Up`.
+pub fn current_state<V>(
+    history: impl IntoIterator<Item = HistoryEntry<V>>,
+) -> HashMap<String, LedgerEntry<V>> {
+    let mut state = HashMap::new();
+    for entry in history {
+        match entry.event {
+            LedgerEvent::Up => {
+                state.insert(
+                    entry.name,
+                    LedgerEntry {
+                        id: entry.id,
+                        checksum: entry.checksum,
+                        applied_at: entry.recorded_at,
+                        format_version: entry.format_version,
+                        details: entry.details,
+                    },
+                );
+            }
+            LedgerEvent::Down => {
+                state.remove(&entry.name);
+            }
+        }
+    }
+    state
+}
+
+/// An applied migration as derived from the ledger history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry<V> {
+    pub id: V,
+    /// `None` for entries recorded before checksums were stored
+    pub checksum: Option<String>,
+    pub applied_at: DateTime<Utc>,
+    /// [`LEDGER_FORMAT_VERSION`] of the record when it was written
+    pub format_version: u32,
+    pub details: LedgerDetails,
+}
+
 #[async_trait::async_trait]
 pub trait VectorTrait: Send + Sync + 'static {
     type Error: Into<VectorBackendError>;
@@ -38,7 +186,7 @@ pub trait VectorTrait: Send + Sync + 'static {
     where
         Self: Sized;
 
-    fn ledger(&self) -> Self::Ledger;
+    fn ledger(&self, options: &LedgerOptions) -> Self::Ledger;
 }
 
 #[async_trait::async_trait]
@@ -48,7 +196,24 @@ pub trait LedgerTrait: Send + Sync {
 
     fn collection_name(&self) -> String;
     async fn ensure(&self) -> Result<(), VectorBackendError>;
-    async fn retrieve(&self) -> Result<HashMap<Self::Key, Self::Value>, VectorBackendError>;
-    async fn insert_many(&self, ids: Vec<Self::Key>) -> Result<(), VectorBackendError>;
+    /// The migrations currently applied, see [`current_state`].
+    async fn retrieve(
+        &self,
+    ) -> Result<HashMap<Self::Key, LedgerEntry<Self::Value>>, VectorBackendError>;
+    /// Every event recorded in the ledger, oldest first.
+    async fn history(&self) -> Result<Vec<HistoryEntry<Self::Value>>, VectorBackendError>;
+    /// Appends `records` to the history.
+    async fn insert_many(&self, records: Vec<LedgerRecord>) -> Result<(), VectorBackendError>;
+    /// Erases events from the history. Rollbacks append a `Down` event instead,
+    /// this is only for rewriting the ledger by hand.
     async fn delete_many(&self, ids: Vec<Self::Value>) -> Result<(), VectorBackendError>;
+
+    /// Takes the migration lock for `owner`, or extends its lease when `owner`
+    /// already holds it. Fails with [`VectorBackendError::Locked`] while another
+    /// owner holds a lease that has not expired.
+    async fn acquire_lock(&self, owner: &str, lease: Duration) -> Result<(), VectorBackendError>;
+    /// Releases the migration lock if it is still held by `owner`.
+    async fn release_lock(&self, owner: &str) -> Result<(), VectorBackendError>;
+    /// Releases the migration lock whoever holds it.
+    async fn force_unlock(&self) -> Result<(), VectorBackendError>;
 }
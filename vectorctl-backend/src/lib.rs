@@ -1,6 +1,15 @@
 pub mod generic;
 
+#[cfg(any(feature = "qdrant-backend", feature = "in-memory-backend"))]
+mod any;
+#[cfg(feature = "in-memory-backend")]
+mod memory;
+#[cfg(feature = "qdrant-backend")]
 mod qdrant;
 
+#[cfg(any(feature = "qdrant-backend", feature = "in-memory-backend"))]
+pub use any::{AnyBackend, AnyLedger, SCHEMES};
+#[cfg(feature = "in-memory-backend")]
+pub use memory::{MemoryBackend as Memory, MemoryClient, MemoryPoint};
 #[cfg(feature = "qdrant-backend")]
 pub use qdrant::QdrantBackend as Qdrant;
@@ -0,0 +1,507 @@
+use crate::generic::{
+    HistoryEntry, LEDGER_FORMAT_VERSION, LedgerDetails, LedgerEntry, LedgerEvent, LedgerOptions,
+    LedgerRecord, LedgerTrait, VectorBackendError, VectorTrait, current_state,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// A point stored in an in-memory collection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryPoint {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub payload: HashMap<String, Value>,
+}
+
+const DEFAULT_COLLECTION_NAME: &str = "_memory_migration";
+
+#[derive(Debug)]
+struct LedgerRow {
+    namespace: Option<String>,
+    name: String,
+    event: LedgerEvent,
+    checksum: Option<String>,
+    recorded_at: DateTime<Utc>,
+    details: LedgerDetails,
+}
+
+#[derive(Debug)]
+struct Lock {
+    owner: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct LedgerState {
+    /// in the order the events were appended
+    rows: Vec<(Uuid, LedgerRow)>,
+    locks: HashMap<Option<String>, Lock>,
+}
+
+/// Collections, points and ledger records kept in process, for tests and
+/// local development. Clones of the backend share the same store.
+#[derive(Debug, Default)]
+pub struct MemoryClient {
+    collections: Mutex<HashMap<String, BTreeMap<String, MemoryPoint>>>,
+    ledgers: Mutex<HashMap<String, LedgerState>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // a panic while holding the guard cannot leave the maps half updated
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn not_found(collection: &str) -> VectorBackendError {
+    VectorBackendError::Other(format!("collection `{collection}` not found"))
+}
+
+impl MemoryClient {
+    pub fn collection_exists(&self, name: &str) -> bool {
+        lock(&self.collections).contains_key(name)
+    }
+
+    pub fn collection_names(&self) -> Vec<String> {
+        let mut names = lock(&self.collections).keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn create_collection(&self, name: &str) -> Result<(), VectorBackendError> {
+        let mut collections = lock(&self.collections);
+        if collections.contains_key(name) {
+            return Err(VectorBackendError::Other(format!(
+                "collection `{name}` already exists"
+            )));
+        }
+        collections.insert(name.to_owned(), BTreeMap::new());
+        Ok(())
+    }
+
+    pub fn delete_collection(&self, name: &str) -> Result<(), VectorBackendError> {
+        lock(&self.collections)
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| not_found(name))
+    }
+
+    pub fn upsert_points(
+        &self,
+        collection: &str,
+        points: Vec<MemoryPoint>,
+    ) -> Result<(), VectorBackendError> {
+        let mut collections = lock(&self.collections);
+        let stored = collections
+            .get_mut(collection)
+            .ok_or_else(|| not_found(collection))?;
+        stored.extend(points.into_iter().map(|point| (point.id.clone(), point)));
+        Ok(())
+    }
+
+    pub fn get_points(
+        &self,
+        collection: &str,
+        ids: &[&str],
+    ) -> Result<Vec<MemoryPoint>, VectorBackendError> {
+        let collections = lock(&self.collections);
+        let stored = collections
+            .get(collection)
+            .ok_or_else(|| not_found(collection))?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| stored.get(*id).cloned())
+            .collect())
+    }
+
+    /// Every point of the collection, ordered by ID.
+    pub fn points(&self, collection: &str) -> Result<Vec<MemoryPoint>, VectorBackendError> {
+        let collections = lock(&self.collections);
+        let stored = collections
+            .get(collection)
+            .ok_or_else(|| not_found(collection))?;
+        Ok(stored.values().cloned().collect())
+    }
+
+    pub fn delete_points(&self, collection: &str, ids: &[&str]) -> Result<(), VectorBackendError> {
+        let mut collections = lock(&self.collections);
+        let stored = collections
+            .get_mut(collection)
+            .ok_or_else(|| not_found(collection))?;
+        ids.iter().for_each(|id| {
+            stored.remove(*id);
+        });
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    pub client: Arc<MemoryClient>,
+}
+
+#[derive(Clone)]
+pub struct Ledger {
+    client: Arc<MemoryClient>,
+    collection_name: String,
+    namespace: Option<String>,
+}
+
+impl Ledger {
+    pub fn new(client: Arc<MemoryClient>, options: &LedgerOptions) -> Self {
+        Self {
+            client,
+            collection_name: options
+                .name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_COLLECTION_NAME.into()),
+            namespace: options.namespace.clone(),
+        }
+    }
+
+    fn state<T>(&self, f: impl FnOnce(&mut LedgerState) -> T) -> T {
+        f(lock(&self.client.ledgers)
+            .entry(self.collection_name.clone())
+            .or_default())
+    }
+}
+
+#[async_trait::async_trait]
+impl LedgerTrait for Ledger {
+    type Key = String;
+    type Value = Uuid;
+
+    fn collection_name(&self) -> String {
+        self.collection_name.clone()
+    }
+
+    async fn ensure(&self) -> Result<(), VectorBackendError> {
+        Ok(())
+    }
+
+    async fn retrieve(
+        &self,
+    ) -> Result<HashMap<Self::Key, LedgerEntry<Self::Value>>, VectorBackendError> {
+        Ok(current_state(self.history().await?))
+    }
+
+    async fn history(&self) -> Result<Vec<HistoryEntry<Self::Value>>, VectorBackendError> {
+        Ok(self.state(|state| {
+            state
+                .rows
+                .iter()
+                .filter(|(_, row)| row.namespace == self.namespace)
+                .map(|(id, row)| HistoryEntry {
+                    id: *id,
+                    name: row.name.clone(),
+                    event: row.event,
+                    checksum: row.checksum.clone(),
+                    recorded_at: row.recorded_at,
+                    format_version: LEDGER_FORMAT_VERSION,
+                    details: row.details.clone(),
+                })
+                .collect()
+        }))
+    }
+
+    async fn insert_many(&self, records: Vec<LedgerRecord>) -> Result<(), VectorBackendError> {
+        let now = Utc::now();
+        self.state(|state| {
+            state.rows.extend(records.into_iter().map(
+                |LedgerRecord {
+                     name,
+                     event,
+                     checksum,
+                     details,
+                 }| {
+                    (
+                        Uuid::now_v7(),
+                        LedgerRow {
+                            namespace: self.namespace.clone(),
+                            name,
+                            event,
+                            checksum,
+                            recorded_at: now,
+                            details,
+                        },
+                    )
+                },
+            ))
+        });
+        Ok(())
+    }
+
+    async fn delete_many(&self, ids: Vec<Self::Value>) -> Result<(), VectorBackendError> {
+        self.state(|state| state.rows.retain(|(id, _)| !ids.contains(id)));
+        Ok(())
+    }
+
+    async fn acquire_lock(&self, owner: &str, lease: Duration) -> Result<(), VectorBackendError> {
+        let now = Utc::now();
+        let lease = chrono::Duration::from_std(lease)
+            .map_err(|err| VectorBackendError::Other(err.to_string()))?;
+
+        self.state(|state| match state.locks.get(&self.namespace) {
+            Some(lock) if lock.owner != owner && lock.expires_at > now => {
+                Err(VectorBackendError::Locked {
+                    owner: lock.owner.clone(),
+                    expires_at: lock.expires_at.to_rfc3339(),
+                })
+            }
+            _ => {
+                state.locks.insert(
+                    self.namespace.clone(),
+                    Lock {
+                        owner: owner.to_owned(),
+                        expires_at: now + lease,
+                    },
+                );
+                Ok(())
+            }
+        })
+    }
+
+    async fn release_lock(&self, owner: &str) -> Result<(), VectorBackendError> {
+        self.state(|state| {
+            if state
+                .locks
+                .get(&self.namespace)
+                .is_some_and(|lock| lock.owner == owner)
+            {
+                state.locks.remove(&self.namespace);
+            }
+        });
+        Ok(())
+    }
+
+    async fn force_unlock(&self) -> Result<(), VectorBackendError> {
+        self.state(|state| state.locks.remove(&self.namespace));
+        Ok(())
+    }
+}
+
+impl VectorTrait for MemoryBackend {
+    type Client = MemoryClient;
+    type Key = String;
+    type Value = Uuid;
+    type Error = VectorBackendError;
+    type Ledger = Ledger;
+
+    /// Starts from an empty store, the URI and API key are ignored.
+    fn new(_uri: &str, _api_key: Option<String>) -> Result<Self, Self::Error> {
+        Ok(Self::default())
+    }
+
+    fn new_with_client(client: Arc<Self::Client>) -> Self {
+        Self { client }
+    }
+
+    fn ledger(&self, options: &LedgerOptions) -> Self::Ledger {
+        Ledger::new(Arc::clone(&self.client), options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str) -> LedgerRecord {
+        LedgerRecord {
+            name: name.into(),
+            event: LedgerEvent::Up,
+            checksum: Some(format!("{name}-checksum")),
+            details: LedgerDetails {
+                revision: Some(name.trim_start_matches("version_").into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ledger_round_trip() {
+        let ledger = MemoryBackend::default().ledger(&LedgerOptions::default());
+        ledger.ensure().await.unwrap();
+        ledger
+            .insert_many(vec![record("version_a"), record("version_b")])
+            .await
+            .unwrap();
+
+        let entries = ledger.retrieve().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries["version_a"].checksum.as_deref(),
+            Some("version_a-checksum")
+        );
+        assert_eq!(entries["version_a"].details.revision.as_deref(), Some("a"));
+
+        ledger
+            .delete_many(vec![entries["version_a"].id])
+            .await
+            .unwrap();
+        let entries = ledger.retrieve().await.unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), vec!["version_b"]);
+    }
+
+    #[tokio::test]
+    async fn test_rollbacks_are_appended_to_the_history() {
+        let ledger = MemoryBackend::default().ledger(&LedgerOptions::default());
+        ledger
+            .insert_many(vec![record("version_a"), record("version_b")])
+            .await
+            .unwrap();
+        ledger
+            .insert_many(vec![LedgerRecord {
+                event: LedgerEvent::Down,
+                ..record("version_b")
+            }])
+            .await
+            .unwrap();
+
+        let entries = ledger.retrieve().await.unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), vec!["version_a"]);
+
+        let history = ledger.history().await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry.event))
+                .collect::<Vec<_>>(),
+            vec![
+                ("version_a", LedgerEvent::Up),
+                ("version_b", LedgerEvent::Up),
+                ("version_b", LedgerEvent::Down),
+            ]
+        );
+
+        ledger.insert_many(vec![record("version_b")]).await.unwrap();
+        let entries = ledger.retrieve().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries["version_b"].id,
+            ledger.history().await.unwrap()[3].id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive_until_released() {
+        let backend = MemoryBackend::default();
+        let (first, second) = (
+            backend.ledger(&LedgerOptions::default()),
+            backend.ledger(&LedgerOptions::default()),
+        );
+        let lease = Duration::from_secs(60);
+
+        first.acquire_lock("first", lease).await.unwrap();
+        first.acquire_lock("first", lease).await.unwrap();
+        assert!(matches!(
+            second.acquire_lock("second", lease).await,
+            Err(VectorBackendError::Locked { owner, .. }) if owner == "first"
+        ));
+
+        second.release_lock("second").await.unwrap();
+        assert!(second.acquire_lock("second", lease).await.is_err());
+
+        first.release_lock("first").await.unwrap();
+        second.acquire_lock("second", lease).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_lock_is_taken_over() {
+        let ledger = MemoryBackend::default().ledger(&LedgerOptions::default());
+        ledger.acquire_lock("first", Duration::ZERO).await.unwrap();
+        ledger
+            .acquire_lock("second", Duration::from_secs(60))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_and_names_keep_ledgers_apart() {
+        let backend = MemoryBackend::default();
+        let ledger = |name: Option<&str>, namespace: Option<&str>| {
+            backend.ledger(&LedgerOptions {
+                name: name.map(str::to_owned),
+                namespace: namespace.map(str::to_owned),
+                ..Default::default()
+            })
+        };
+        let lease = Duration::from_secs(60);
+
+        ledger(None, Some("search"))
+            .insert_many(vec![record("version_a")])
+            .await
+            .unwrap();
+        ledger(None, Some("search"))
+            .acquire_lock("search", lease)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ledger(None, Some("search")).retrieve().await.unwrap().len(),
+            1
+        );
+        assert!(
+            ledger(None, Some("billing"))
+                .retrieve()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(ledger(None, None).retrieve().await.unwrap().is_empty());
+        assert!(
+            ledger(Some("_other"), Some("search"))
+                .retrieve()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        ledger(None, Some("billing"))
+            .acquire_lock("billing", lease)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_collections_store_points() {
+        let client = MemoryClient::default();
+        client.create_collection("docs").unwrap();
+        assert!(client.create_collection("docs").is_err());
+
+        client
+            .upsert_points(
+                "docs",
+                ["b", "a"]
+                    .into_iter()
+                    .map(|id| MemoryPoint {
+                        id: id.into(),
+                        vector: vec![0.5; 4],
+                        payload: HashMap::from([("title".into(), Value::from(id))]),
+                    })
+                    .collect(),
+            )
+            .unwrap();
+        assert_eq!(
+            client
+                .points("docs")
+                .unwrap()
+                .into_iter()
+                .map(|point| point.id)
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+
+        client.delete_points("docs", &["a"]).unwrap();
+        assert_eq!(client.get_points("docs", &["a", "b"]).unwrap().len(), 1);
+
+        client.delete_collection("docs").unwrap();
+        assert!(!client.collection_exists("docs"));
+        assert!(client.points("docs").is_err());
+    }
+}
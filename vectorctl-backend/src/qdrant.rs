@@ -1,22 +1,79 @@
-use crate::generic::{LedgerTrait, VectorBackendError, VectorTrait};
+use crate::generic::{
+    HistoryEntry, LEDGER_FORMAT_VERSION, LedgerDetails, LedgerEntry, LedgerEvent, LedgerOptions,
+    LedgerRecord, LedgerTrait, VectorBackendError, VectorTrait, current_state,
+};
 use chrono::{DateTime, Utc};
 use qdrant_client::{
-    Payload as QdrantPayload, Qdrant,
+    Payload as QdrantPayload, Qdrant, QdrantError,
     config::CompressionEncoding,
     qdrant::{
-        CreateCollectionBuilder, DeletePointsBuilder, Distance, PointId, PointStruct,
-        PointsIdsList, ScrollPointsBuilder, UpsertPointsBuilder, Value, VectorParamsBuilder,
+        Condition, CreateCollectionBuilder, DeletePoints, DeletePointsBuilder, Distance, Filter,
+        GetPoints, GetPointsBuilder, GetResponse, PointId, PointStruct, PointsIdsList,
+        PointsOperationResponse, Range, RetrievedPoint, ScrollPoints, ScrollPointsBuilder,
+        ScrollResponse, UpsertPoints, UpsertPointsBuilder, Value, VectorParamsBuilder,
         point_id::PointIdOptions,
     },
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, convert::TryFrom, sync::Arc};
+use std::{collections::HashMap, convert::TryFrom, sync::Arc, time::Duration};
 use uuid::Uuid;
 
+/// ID of the point holding the migration lock in the ledger collection.
+const LOCK_POINT_ID: Uuid = Uuid::from_u128(0x7665_6374_6f72_6374_6c5f_6c6f_636b_0000);
+
+const DEFAULT_COLLECTION_NAME: &str = "_qdrant_migration";
+
+/// Every namespace has its own lock point, derived from [`LOCK_POINT_ID`].
+fn lock_point_id(namespace: Option<&str>) -> Uuid {
+    match namespace {
+        Some(namespace) => Uuid::new_v5(&LOCK_POINT_ID, namespace.as_bytes()),
+        None => LOCK_POINT_ID,
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 struct Payload {
     name: String,
+    /// absent from records written before rollbacks were recorded
+    #[serde(default)]
+    event: LedgerEvent,
+    /// when the event was recorded, kept under its original name so older
+    /// records still parse
     applied_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    /// absent from records written before the format was versioned
+    #[serde(default)]
+    format_version: u32,
+    #[serde(flatten)]
+    details: LedgerDetails,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+struct Lock {
+    owner: String,
+    #[serde(with = "chrono::serde::ts_milliseconds")]
+    expires_at: DateTime<Utc>,
+}
+
+impl Lock {
+    fn into_point(self, id: Uuid) -> Result<PointStruct, VectorBackendError> {
+        Ok(PointStruct::new(
+            id.to_string(),
+            vec![0.0_f32; 1],
+            QdrantPayload::try_from(serde_json::to_value(self)?)?,
+        ))
+    }
+}
+
+impl TryFrom<HashMap<String, Value>> for Lock {
+    type Error = serde_json::Error;
+
+    fn try_from(map: HashMap<String, Value>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::to_value(map)?)
+    }
 }
 
 impl TryFrom<HashMap<String, Value>> for Payload {
@@ -39,6 +96,261 @@ impl TryFrom<Payload> for PointStruct {
     }
 }
 
+/// Number of ledger points requested per `scroll` call.
+const SCROLL_PAGE_SIZE: u32 = 256;
+
+/// The part of the Qdrant client the ledger pages through, kept apart so that
+/// pagination can be exercised without a running Qdrant.
+#[async_trait::async_trait]
+trait ScrollClient: Send + Sync {
+    async fn scroll(&self, request: ScrollPoints) -> Result<ScrollResponse, QdrantError>;
+}
+
+#[async_trait::async_trait]
+impl ScrollClient for Qdrant {
+    async fn scroll(&self, request: ScrollPoints) -> Result<ScrollResponse, QdrantError> {
+        Qdrant::scroll(self, request).await
+    }
+}
+
+/// Follows `next_page_offset` until every point of the collection has been read.
+async fn scroll_all(
+    client: &impl ScrollClient,
+    collection_name: &str,
+) -> Result<Vec<RetrievedPoint>, VectorBackendError> {
+    let mut points = Vec::new();
+    let mut offset: Option<PointId> = None;
+
+    loop {
+        let mut builder = ScrollPointsBuilder::new(collection_name)
+            .limit(SCROLL_PAGE_SIZE)
+            .with_payload(true)
+            .with_vectors(false);
+        if let Some(offset) = offset.take() {
+            builder = builder.offset(offset);
+        }
+
+        let page = client.scroll(builder.build()).await?;
+        points.extend(page.result);
+
+        match page.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => return Ok(points),
+        }
+    }
+}
+
+/// Reads the events of `namespace` oldest first, skipping lock points and the
+/// records of other namespaces sharing the collection.
+async fn read_history(
+    client: &impl ScrollClient,
+    collection_name: &str,
+    namespace: Option<&str>,
+) -> Result<Vec<HistoryEntry<Uuid>>, VectorBackendError> {
+    let lock_id = lock_point_id(namespace);
+    let mut history = scroll_all(client, collection_name)
+        .await?
+        .into_iter()
+        .filter_map(|point| {
+            let id = match point.id?.point_id_options? {
+                PointIdOptions::Uuid(ref s) => Uuid::try_parse(s).ok()?,
+                PointIdOptions::Num(_) => return None,
+            };
+            if id == lock_id {
+                return None;
+            }
+
+            let payload = Payload::try_from(point.payload).ok()?;
+            if payload.namespace.as_deref() != namespace {
+                return None;
+            }
+
+            Some(HistoryEntry {
+                id,
+                name: payload.name,
+                event: payload.event,
+                checksum: payload.checksum,
+                recorded_at: payload.applied_at,
+                format_version: payload.format_version,
+                details: payload.details,
+            })
+        })
+        .collect::<Vec<_>>();
+    // v7 IDs break ties between events recorded in the same millisecond
+    history.sort_by_key(|entry| (entry.recorded_at, entry.id));
+    Ok(history)
+}
+
+/// Reads the migrations `namespace` currently has applied.
+async fn read_ledger(
+    client: &impl ScrollClient,
+    collection_name: &str,
+    namespace: Option<&str>,
+) -> Result<HashMap<String, LedgerEntry<Uuid>>, VectorBackendError> {
+    Ok(current_state(
+        read_history(client, collection_name, namespace).await?,
+    ))
+}
+
+/// The part of the Qdrant client the migration lock goes through, kept apart so
+/// that the lock protocol can be exercised without a running Qdrant.
+#[async_trait::async_trait]
+trait LockClient: Send + Sync {
+    async fn upsert_points(
+        &self,
+        request: UpsertPoints,
+    ) -> Result<PointsOperationResponse, QdrantError>;
+    async fn get_points(&self, request: GetPoints) -> Result<GetResponse, QdrantError>;
+    async fn delete_points(
+        &self,
+        request: DeletePoints,
+    ) -> Result<PointsOperationResponse, QdrantError>;
+}
+
+#[async_trait::async_trait]
+impl LockClient for Qdrant {
+    async fn upsert_points(
+        &self,
+        request: UpsertPoints,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        Qdrant::upsert_points(self, request).await
+    }
+
+    async fn get_points(&self, request: GetPoints) -> Result<GetResponse, QdrantError> {
+        Qdrant::get_points(self, request).await
+    }
+
+    async fn delete_points(
+        &self,
+        request: DeletePoints,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        Qdrant::delete_points(self, request).await
+    }
+}
+
+fn lock_point(namespace: Option<&str>) -> PointId {
+    PointId::from(lock_point_id(namespace).to_string())
+}
+
+/// Takes the lock of `namespace` for `owner` until `now + lease`.
+///
+/// The protocol is a single conditional upsert followed by a read-back, and
+/// relies on three behaviours of Qdrant:
+///
+/// - an upsert with an `update_filter` still inserts a point that does not
+///   exist yet,
+/// - it leaves an existing point untouched unless the point matches the
+///   filter,
+/// - with `wait` set, the write is visible to the read that follows it.
+///
+/// A free lock is therefore taken, our own lease renewed and an expired one
+/// taken over, while a live lock held by someone else survives; the point read
+/// back tells us who won.
+async fn acquire_lock(
+    client: &impl LockClient,
+    collection_name: &str,
+    namespace: Option<&str>,
+    owner: &str,
+    lease: Duration,
+    now: DateTime<Utc>,
+) -> Result<(), VectorBackendError> {
+    let lease = chrono::Duration::from_std(lease)
+        .map_err(|err| VectorBackendError::Other(err.to_string()))?;
+    let point = Lock {
+        owner: owner.to_owned(),
+        expires_at: now + lease,
+    }
+    .into_point(lock_point_id(namespace))?;
+
+    client
+        .upsert_points(
+            UpsertPointsBuilder::new(collection_name, vec![point])
+                .update_filter(Filter::should([
+                    Condition::range(
+                        "expires_at",
+                        Range {
+                            lt: Some(now.timestamp_millis() as f64),
+                            ..Default::default()
+                        },
+                    ),
+                    Condition::matches("owner", owner.to_owned()),
+                ]))
+                .wait(true)
+                .build(),
+        )
+        .await?;
+
+    match current_lock(client, collection_name, namespace).await? {
+        Some(lock) if lock.owner == owner => Ok(()),
+        Some(lock) => Err(VectorBackendError::Locked {
+            owner: lock.owner,
+            expires_at: lock.expires_at.to_rfc3339(),
+        }),
+        None => Err(VectorBackendError::Other(
+            "migration lock vanished while acquiring it".into(),
+        )),
+    }
+}
+
+/// Deletes the lock of `namespace` only if `owner` still holds it.
+async fn release_lock(
+    client: &impl LockClient,
+    collection_name: &str,
+    namespace: Option<&str>,
+    owner: &str,
+) -> Result<(), VectorBackendError> {
+    let request = DeletePointsBuilder::new(collection_name)
+        .points(Filter::must([
+            Condition::has_id([lock_point(namespace)]),
+            Condition::matches("owner", owner.to_owned()),
+        ]))
+        .wait(true)
+        .build();
+
+    client.delete_points(request).await?;
+    Ok(())
+}
+
+/// Deletes the lock of `namespace` whoever holds it.
+async fn force_unlock(
+    client: &impl LockClient,
+    collection_name: &str,
+    namespace: Option<&str>,
+) -> Result<(), VectorBackendError> {
+    let request = DeletePointsBuilder::new(collection_name)
+        .points(PointsIdsList {
+            ids: vec![lock_point(namespace)],
+        })
+        .wait(true)
+        .build();
+
+    client.delete_points(request).await?;
+    Ok(())
+}
+
+async fn current_lock(
+    client: &impl LockClient,
+    collection_name: &str,
+    namespace: Option<&str>,
+) -> Result<Option<Lock>, VectorBackendError> {
+    let response = client
+        .get_points(
+            GetPointsBuilder::new(collection_name, vec![lock_point(namespace)])
+                .with_payload(true)
+                .with_vectors(false)
+                .build(),
+        )
+        .await?;
+
+    response
+        .result
+        .into_iter()
+        .next()
+        .map(|point| Lock::try_from(point.payload).map_err(VectorBackendError::from))
+        .transpose()
+}
+
+#[derive(Clone)]
 pub struct QdrantBackend {
     pub client: Arc<Qdrant>,
 }
@@ -54,11 +366,20 @@ impl QdrantBackend {
 #[derive(Clone)]
 pub struct Ledger {
     client: Arc<Qdrant>,
+    collection_name: String,
+    namespace: Option<String>,
 }
 
 impl Ledger {
-    pub fn new(client: Arc<Qdrant>) -> Self {
-        Self { client }
+    pub fn new(client: Arc<Qdrant>, options: &LedgerOptions) -> Self {
+        Self {
+            client,
+            collection_name: options
+                .name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_COLLECTION_NAME.into()),
+            namespace: options.namespace.clone(),
+        }
     }
 }
 
@@ -68,7 +389,7 @@ impl LedgerTrait for Ledger {
     type Value = Uuid;
 
     fn collection_name(&self) -> String {
-        "_qdrant_migration".into()
+        self.collection_name.clone()
     }
 
     async fn ensure(&self) -> Result<(), VectorBackendError> {
@@ -88,43 +409,49 @@ impl LedgerTrait for Ledger {
         Ok(())
     }
 
-    async fn retrieve(&self) -> Result<HashMap<Self::Key, Self::Value>, VectorBackendError> {
-        let scroll = self
-            .client
-            .scroll(
-                ScrollPointsBuilder::new(self.collection_name())
-                    .with_payload(true)
-                    .with_vectors(false),
-            )
-            .await?;
+    async fn retrieve(
+        &self,
+    ) -> Result<HashMap<Self::Key, LedgerEntry<Self::Value>>, VectorBackendError> {
+        read_ledger(
+            self.client.as_ref(),
+            &self.collection_name(),
+            self.namespace.as_deref(),
+        )
+        .await
+    }
 
-        Ok(scroll
-            .result
-            .into_iter()
-            .filter_map(|point| {
-                let id = match point.id?.point_id_options? {
-                    PointIdOptions::Uuid(ref s) => Uuid::try_parse(s).ok()?,
-                    PointIdOptions::Num(_) => return None,
-                };
-
-                Payload::try_from(point.payload)
-                    .ok()
-                    .map(|payload| (payload.name, id))
-            })
-            .collect())
+    async fn history(&self) -> Result<Vec<HistoryEntry<Self::Value>>, VectorBackendError> {
+        read_history(
+            self.client.as_ref(),
+            &self.collection_name(),
+            self.namespace.as_deref(),
+        )
+        .await
     }
 
-    async fn insert_many(&self, ids: Vec<Self::Key>) -> Result<(), VectorBackendError> {
+    async fn insert_many(&self, records: Vec<LedgerRecord>) -> Result<(), VectorBackendError> {
         let now = Utc::now();
 
-        let points = ids
+        let points = records
             .into_iter()
-            .map(|id| {
-                PointStruct::try_from(Payload {
-                    name: id,
-                    applied_at: now,
-                })
-            })
+            .map(
+                |LedgerRecord {
+                     name,
+                     event,
+                     checksum,
+                     details,
+                 }| {
+                    PointStruct::try_from(Payload {
+                        name,
+                        event,
+                        applied_at: now,
+                        checksum,
+                        namespace: self.namespace.clone(),
+                        format_version: LEDGER_FORMAT_VERSION,
+                        details,
+                    })
+                },
+            )
             .collect::<Result<Vec<_>, _>>()?;
 
         self.client
@@ -144,11 +471,43 @@ impl LedgerTrait for Ledger {
 
         let builder = DeletePointsBuilder::new(self.collection_name())
             .points(points)
+            .wait(true)
             .build();
 
         self.client.delete_points(builder).await?;
         Ok(())
     }
+
+    async fn acquire_lock(&self, owner: &str, lease: Duration) -> Result<(), VectorBackendError> {
+        acquire_lock(
+            self.client.as_ref(),
+            &self.collection_name,
+            self.namespace.as_deref(),
+            owner,
+            lease,
+            Utc::now(),
+        )
+        .await
+    }
+
+    async fn release_lock(&self, owner: &str) -> Result<(), VectorBackendError> {
+        release_lock(
+            self.client.as_ref(),
+            &self.collection_name,
+            self.namespace.as_deref(),
+            owner,
+        )
+        .await
+    }
+
+    async fn force_unlock(&self) -> Result<(), VectorBackendError> {
+        force_unlock(
+            self.client.as_ref(),
+            &self.collection_name,
+            self.namespace.as_deref(),
+        )
+        .await
+    }
 }
 
 impl VectorTrait for QdrantBackend {
@@ -171,7 +530,502 @@ impl VectorTrait for QdrantBackend {
         Self { client }
     }
 
-    fn ledger(&self) -> Self::Ledger {
-        Ledger::new(Arc::clone(&self.client))
+    fn ledger(&self, options: &LedgerOptions) -> Self::Ledger {
+        Ledger::new(Arc::clone(&self.client), options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qdrant_client::qdrant::{
+        Match, condition::ConditionOneOf, r#match::MatchValue, points_selector::PointsSelectorOneOf,
+    };
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Serves a fixed set of points the way Qdrant pages them: ordered by ID,
+    /// `limit` at a time, starting at `offset` inclusive.
+    struct FakeScrollClient {
+        points: Vec<RetrievedPoint>,
+        calls: AtomicUsize,
+    }
+
+    impl FakeScrollClient {
+        fn with_ledger(size: usize) -> Self {
+            Self::with_namespaces(size, |_| None)
+        }
+
+        fn with_namespaces(size: usize, namespace: fn(usize) -> Option<String>) -> Self {
+            Self::with_payloads((0..size).map(|ix| Payload {
+                name: format!("version_{ix:05}"),
+                event: LedgerEvent::Up,
+                applied_at: Utc::now(),
+                checksum: (ix % 2 == 0).then(|| format!("{ix:064x}")),
+                namespace: namespace(ix),
+                format_version: LEDGER_FORMAT_VERSION,
+                details: LedgerDetails {
+                    revision: Some(format!("rev{ix}")),
+                    duration: Some(Duration::from_millis(ix as u64)),
+                    ..Default::default()
+                },
+            }))
+        }
+
+        fn with_payloads(payloads: impl IntoIterator<Item = Payload>) -> Self {
+            let points = payloads
+                .into_iter()
+                .map(|payload| RetrievedPoint {
+                    id: Some(PointId::from(Uuid::now_v7().to_string())),
+                    payload: QdrantPayload::try_from(serde_json::to_value(payload).unwrap())
+                        .unwrap()
+                        .into(),
+                    ..Default::default()
+                })
+                .collect();
+            Self {
+                points,
+                calls: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ScrollClient for FakeScrollClient {
+        async fn scroll(&self, request: ScrollPoints) -> Result<ScrollResponse, QdrantError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let start = request
+                .offset
+                .map(|offset| {
+                    self.points
+                        .iter()
+                        .position(|point| point.id.as_ref() == Some(&offset))
+                        .expect("offset must be a known point")
+                })
+                .unwrap_or_default();
+            let limit = request.limit.unwrap_or(10) as usize;
+            let end = (start + limit).min(self.points.len());
+
+            Ok(ScrollResponse {
+                result: self.points[start..end].to_vec(),
+                next_page_offset: self.points.get(end).and_then(|point| point.id.clone()),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_ledger_follows_every_page() {
+        let size = SCROLL_PAGE_SIZE as usize * 10 + 7;
+        let client = FakeScrollClient::with_ledger(size);
+
+        let ledger = read_ledger(&client, DEFAULT_COLLECTION_NAME, None)
+            .await
+            .unwrap();
+
+        assert_eq!(ledger.len(), size);
+        assert_eq!(
+            ledger["version_00000"].checksum.as_deref(),
+            Some(format!("{:064x}", 0).as_str())
+        );
+        assert_eq!(ledger["version_00001"].checksum, None);
+        assert_eq!(
+            ledger["version_00003"].details.duration,
+            Some(Duration::from_millis(3))
+        );
+        assert_eq!(
+            ledger["version_00003"].format_version,
+            LEDGER_FORMAT_VERSION
+        );
+        assert!(ledger.contains_key(&format!("version_{:05}", size - 1)));
+        assert_eq!(client.calls.load(Ordering::SeqCst), 11);
+    }
+
+    #[tokio::test]
+    async fn test_read_ledger_only_returns_its_namespace() {
+        let client = FakeScrollClient::with_namespaces(6, |ix| match ix % 3 {
+            0 => None,
+            1 => Some("search".into()),
+            _ => Some("billing".into()),
+        });
+
+        let search = read_ledger(&client, DEFAULT_COLLECTION_NAME, Some("search"))
+            .await
+            .unwrap();
+        assert_eq!(search.len(), 2);
+        assert!(search.contains_key("version_00001"));
+        assert!(search.contains_key("version_00004"));
+
+        let default = read_ledger(&client, DEFAULT_COLLECTION_NAME, None)
+            .await
+            .unwrap();
+        assert_eq!(default.len(), 2);
+        assert!(default.contains_key("version_00000"));
+
+        assert_eq!(lock_point_id(None), LOCK_POINT_ID);
+        assert_ne!(
+            lock_point_id(Some("search")),
+            lock_point_id(Some("billing"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_ledger_replays_the_history() {
+        let at = |secs| DateTime::from_timestamp(secs, 0).unwrap();
+        let payload = |name: &str, event, secs| Payload {
+            name: name.into(),
+            event,
+            applied_at: at(secs),
+            checksum: None,
+            namespace: None,
+            format_version: LEDGER_FORMAT_VERSION,
+            details: LedgerDetails::default(),
+        };
+        // scrolled in ID order, which is not the order the events happened in
+        let client = FakeScrollClient::with_payloads([
+            payload("version_b", LedgerEvent::Down, 30),
+            payload("version_a", LedgerEvent::Up, 10),
+            payload("version_b", LedgerEvent::Up, 20),
+            payload("version_c", LedgerEvent::Down, 50),
+            payload("version_c", LedgerEvent::Up, 40),
+            payload("version_c", LedgerEvent::Up, 60),
+        ]);
+
+        let history = read_history(&client, DEFAULT_COLLECTION_NAME, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| entry.recorded_at)
+                .collect::<Vec<_>>(),
+            [10, 20, 30, 40, 50, 60].map(at)
+        );
+
+        let ledger = read_ledger(&client, DEFAULT_COLLECTION_NAME, None)
+            .await
+            .unwrap();
+        assert_eq!(ledger.len(), 2);
+        assert!(ledger.contains_key("version_a"));
+        assert_eq!(ledger["version_c"].applied_at, at(60));
+    }
+
+    #[test]
+    fn test_payload_format_versions() {
+        let legacy: Payload = serde_json::from_value(serde_json::json!({
+            "name": "version_a",
+            "applied_at": "2025-05-22T14:59:10Z",
+        }))
+        .unwrap();
+        assert_eq!(legacy.format_version, 0);
+        assert_eq!(legacy.event, LedgerEvent::Up);
+        assert_eq!(legacy.details, LedgerDetails::default());
+
+        let payload = Payload {
+            name: "version_b".into(),
+            event: LedgerEvent::Down,
+            applied_at: Utc::now(),
+            checksum: None,
+            namespace: None,
+            format_version: LEDGER_FORMAT_VERSION,
+            details: LedgerDetails {
+                revision: Some("b".into()),
+                down_revision: vec!["a".into()],
+                duration: Some(Duration::from_millis(1500)),
+                host: Some("ci".into()),
+                user: Some("deploy".into()),
+                vectorctl_version: Some("0.1.0".into()),
+                migrator_version: Some("1.2.3".into()),
+                stamped: true,
+            },
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["duration_ms"], 1500);
+        assert_eq!(value["event"], "down");
+        assert_eq!(value["stamped"], true);
+        assert_eq!(value["format_version"], LEDGER_FORMAT_VERSION);
+        assert_eq!(serde_json::from_value::<Payload>(value).unwrap(), payload);
+    }
+
+    #[tokio::test]
+    async fn test_read_ledger_empty_collection() {
+        let client = FakeScrollClient::with_ledger(0);
+
+        let ledger = read_ledger(&client, DEFAULT_COLLECTION_NAME, None)
+            .await
+            .unwrap();
+
+        assert!(ledger.is_empty());
+        assert_eq!(client.calls.load(Ordering::SeqCst), 1);
+    }
+
+    /// Keeps points in memory and writes them the way Qdrant does, evaluating
+    /// only the filter conditions the lock protocol sends.
+    #[derive(Default)]
+    struct FakeLockClient {
+        points: Mutex<Vec<(PointId, HashMap<String, Value>)>>,
+    }
+
+    fn filter_matches(filter: &Filter, id: &PointId, payload: &HashMap<String, Value>) -> bool {
+        let holds = |condition: &Condition| condition_matches(condition, id, payload);
+        filter.must.iter().all(holds)
+            && !filter.must_not.iter().any(holds)
+            && (filter.should.is_empty() || filter.should.iter().any(holds))
+    }
+
+    fn condition_matches(
+        condition: &Condition,
+        id: &PointId,
+        payload: &HashMap<String, Value>,
+    ) -> bool {
+        let payload = serde_json::to_value(payload).unwrap();
+        match condition.condition_one_of.as_ref() {
+            Some(ConditionOneOf::HasId(has_id)) => has_id.has_id.contains(id),
+            Some(ConditionOneOf::Field(field)) => {
+                let value = &payload[&field.key];
+                match (&field.r#match, &field.range) {
+                    (
+                        Some(Match {
+                            match_value: Some(MatchValue::Keyword(keyword)),
+                        }),
+                        None,
+                    ) => value.as_str() == Some(keyword),
+                    (None, Some(range)) => value.as_f64().is_some_and(|value| {
+                        range.lt.is_none_or(|lt| value < lt)
+                            && range.lte.is_none_or(|lte| value <= lte)
+                            && range.gt.is_none_or(|gt| value > gt)
+                            && range.gte.is_none_or(|gte| value >= gte)
+                    }),
+                    _ => panic!("unsupported field condition in fake client: {field:?}"),
+                }
+            }
+            other => panic!("unsupported condition in fake client: {other:?}"),
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LockClient for FakeLockClient {
+        async fn upsert_points(
+            &self,
+            request: UpsertPoints,
+        ) -> Result<PointsOperationResponse, QdrantError> {
+            assert_eq!(request.wait, Some(true), "lock writes must wait");
+            let mut points = self.points.lock().unwrap();
+            for point in request.points {
+                let id = point.id.unwrap();
+                match points.iter_mut().find(|(existing, _)| *existing == id) {
+                    // an existing point is only overwritten when it matches
+                    Some((_, payload)) => {
+                        if request
+                            .update_filter
+                            .as_ref()
+                            .is_none_or(|filter| filter_matches(filter, &id, payload))
+                        {
+                            *payload = point.payload;
+                        }
+                    }
+                    // a missing one is inserted whatever the filter says
+                    None => points.push((id, point.payload)),
+                }
+            }
+            Ok(PointsOperationResponse::default())
+        }
+
+        async fn get_points(&self, request: GetPoints) -> Result<GetResponse, QdrantError> {
+            let points = self.points.lock().unwrap();
+            Ok(GetResponse {
+                result: points
+                    .iter()
+                    .filter(|(id, _)| request.ids.contains(id))
+                    .map(|(id, payload)| RetrievedPoint {
+                        id: Some(id.clone()),
+                        payload: payload.clone(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+        }
+
+        async fn delete_points(
+            &self,
+            request: DeletePoints,
+        ) -> Result<PointsOperationResponse, QdrantError> {
+            assert_eq!(request.wait, Some(true), "lock writes must wait");
+            let selector = request
+                .points
+                .and_then(|points| points.points_selector_one_of);
+            self.points
+                .lock()
+                .unwrap()
+                .retain(|(id, payload)| match &selector {
+                    Some(PointsSelectorOneOf::Points(list)) => !list.ids.contains(id),
+                    Some(PointsSelectorOneOf::Filter(filter)) => {
+                        !filter_matches(filter, id, payload)
+                    }
+                    None => true,
+                });
+            Ok(PointsOperationResponse::default())
+        }
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    async fn holder(client: &FakeLockClient, namespace: Option<&str>) -> Option<Lock> {
+        current_lock(client, DEFAULT_COLLECTION_NAME, namespace)
+            .await
+            .unwrap()
+    }
+
+    const LEASE: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn test_lock_is_taken_when_free_and_renewed_by_its_owner() {
+        let client = FakeLockClient::default();
+
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "first",
+            LEASE,
+            at(0),
+        )
+        .await
+        .unwrap();
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "first",
+            LEASE,
+            at(30),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            holder(&client, None).await,
+            Some(Lock {
+                owner: "first".into(),
+                expires_at: at(90),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_contention_keeps_the_live_holder() {
+        let client = FakeLockClient::default();
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "first",
+            LEASE,
+            at(0),
+        )
+        .await
+        .unwrap();
+
+        let result = acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "second",
+            LEASE,
+            at(59),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(VectorBackendError::Locked { owner, expires_at })
+                if owner == "first" && expires_at == at(60).to_rfc3339()
+        ));
+        assert_eq!(holder(&client, None).await.unwrap().expires_at, at(60));
+
+        // other namespaces have their own lock
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            Some("search"),
+            "second",
+            LEASE,
+            at(59),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_lock_is_taken_over() {
+        let client = FakeLockClient::default();
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "first",
+            LEASE,
+            at(0),
+        )
+        .await
+        .unwrap();
+
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "second",
+            LEASE,
+            at(61),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(holder(&client, None).await.unwrap().owner, "second");
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_only_released_by_its_owner() {
+        let client = FakeLockClient::default();
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "first",
+            LEASE,
+            at(0),
+        )
+        .await
+        .unwrap();
+
+        release_lock(&client, DEFAULT_COLLECTION_NAME, None, "second")
+            .await
+            .unwrap();
+        assert_eq!(holder(&client, None).await.unwrap().owner, "first");
+
+        release_lock(&client, DEFAULT_COLLECTION_NAME, None, "first")
+            .await
+            .unwrap();
+        assert_eq!(holder(&client, None).await, None);
+
+        acquire_lock(
+            &client,
+            DEFAULT_COLLECTION_NAME,
+            None,
+            "second",
+            LEASE,
+            at(1),
+        )
+        .await
+        .unwrap();
+        force_unlock(&client, DEFAULT_COLLECTION_NAME, None)
+            .await
+            .unwrap();
+        assert_eq!(holder(&client, None).await, None);
     }
 }
@@ -1,6 +1,16 @@
+use std::process::ExitCode;
+
 use vectorctl_cli::CliError;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<CliError>> {
-    vectorctl_cli::main().await.map_err(Box::new)
+async fn main() -> ExitCode {
+    match vectorctl_cli::main().await {
+        Ok(()) => ExitCode::SUCCESS,
+        // the migration process already reported its own error
+        Err(err @ CliError::Migration(_)) => ExitCode::from(err.exit_code()),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(err.exit_code())
+        }
+    }
 }
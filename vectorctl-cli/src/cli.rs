@@ -2,7 +2,7 @@ use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 use thiserror::Error;
 
-use crate::commands::{MigrateSubcommands, run_migrate_command};
+use crate::commands::{LedgerArgs, MigrateSubcommands, OutputFormat, run_migrate_command};
 
 #[derive(Debug, Error)]
 pub enum CliError {
@@ -12,6 +12,32 @@ pub enum CliError {
     Migrate(#[from] crate::commands::MigrateError),
     #[error("custom: {0}")]
     Custom(String),
+    #[error("Migration process failed with {0}")]
+    Migration(std::process::ExitStatus),
+}
+
+impl CliError {
+    /// Exit code to report, passing through the migration process's own code.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Migration(status) => status
+                .code()
+                .or_else(|| {
+                    #[cfg(unix)]
+                    {
+                        std::os::unix::process::ExitStatusExt::signal(status)
+                            .map(|signal| 128 + signal)
+                    }
+                    #[cfg(not(unix))]
+                    {
+                        None
+                    }
+                })
+                .and_then(|code| u8::try_from(code).ok())
+                .unwrap_or(1),
+            _ => 1,
+        }
+    }
 }
 
 #[derive(Subcommand, PartialEq, Eq, Debug)]
@@ -30,7 +56,7 @@ pub enum Commands {
             global = true,
             short = 'u',
             long,
-            help = "database url",
+            help = "database url, its scheme selects the backend",
             env = "DATABASE_URL",
             default_value = "http://localhost:6334"
         )]
@@ -43,6 +69,24 @@ pub enum Commands {
             env = "DATABASE_API_KEY"
         )]
         api_key: Option<String>,
+        #[arg(
+            global = true,
+            short = 'b',
+            long,
+            help = "prebuilt migration binary to run instead of the migration crate",
+            env = "MIGRATION_BINARY"
+        )]
+        binary: Option<PathBuf>,
+        #[arg(
+            global = true,
+            long,
+            value_enum,
+            default_value_t = OutputFormat::Text,
+            help = "output format of status and run reports"
+        )]
+        format: OutputFormat,
+        #[command(flatten)]
+        ledger: LedgerArgs,
         #[command(subcommand)]
         command: Option<MigrateSubcommands>,
     },
@@ -61,10 +105,24 @@ pub async fn main() -> Result<(), CliError> {
     match cli.command {
         Commands::Migrate {
             api_key,
+            binary,
             command,
             database_url,
+            format,
+            ledger,
             migration_dir,
-        } => run_migrate_command(command, migration_dir, database_url, api_key).await?,
+        } => {
+            run_migrate_command(
+                command,
+                migration_dir,
+                database_url,
+                api_key,
+                binary,
+                format,
+                ledger,
+            )
+            .await?
+        }
     }
 
     Ok(())
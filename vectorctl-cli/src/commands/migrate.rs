@@ -1,18 +1,29 @@
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::Utc;
+use proc_macro2::{LineColumn, TokenTree};
 use rand::{TryRngCore, rand_core::OsError, rngs::OsRng};
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    time::SystemTime,
+};
 use thiserror::Error;
 use tokio::fs;
 use tokio_stream::{StreamExt, wrappers::ReadDirStream};
 use vectorctl_template::{
-    MigrationTemplate, RenderError, migrator::MigratorTemplate, revision::RevisionTemplate,
+    MigrationTemplate, Registry, RenderError, migrator::MigratorTemplate, registry::REGISTRY,
+    revision::RevisionTemplate,
 };
 
 pub const REVISION_PREFIX: &str = "version";
 const DATE_FMT: &str = "%Y-%m-%dT%H:%M:%S";
 const DATE_FILE_FMT: &str = "%Y%m%d_%H%M%S";
 const MIGRATOR_FILENAME: &str = "lib.rs";
+const CACHED_BINARY_DIR: &str = "target/vectorctl";
+const MANIFEST_FILENAME: &str = "Cargo.toml";
+const LOCKFILE_FILENAME: &str = "Cargo.lock";
+/// Source files cargo read to compile the cached binary, one per line.
+const CACHED_INPUTS_FILENAME: &str = "inputs";
 
 #[derive(Debug, Error)]
 pub enum MigrateError {
@@ -20,10 +31,22 @@ pub enum MigrateError {
     Io(#[from] std::io::Error),
     #[error("Syntax parsing failed: {0}")]
     Parser(#[from] syn::Error),
+    #[error("No `mod {REVISION_PREFIX}_*;` declaration in {} to add the revision after", .0.display())]
+    MissingModDeclaration(PathBuf),
+    #[error("No `vec![...]` returned by `MigratorTrait::migrations()` in {} to add the revision to", .0.display())]
+    MissingMigrationList(PathBuf),
     #[error("Secure RNG failed: {0}")]
     OsRng(#[from] OsError),
     #[error("Template rendering failed: {0}")]
     Render(#[from] RenderError),
+    #[error("Invalid migration crate manifest: {0}")]
+    Manifest(#[from] toml::de::Error),
+    #[error("Invalid cargo output: {0}")]
+    CargoOutput(#[from] serde_json::Error),
+    #[error("Building the migration crate failed with {0}")]
+    Build(ExitStatus),
+    #[error("Building the migration crate produced no binary")]
+    MissingBinary,
 }
 
 type Result<T> = std::result::Result<T, MigrateError>;
@@ -57,28 +80,74 @@ fn src_dir(base: impl AsRef<Path>) -> PathBuf {
     }
 }
 
+/// `template-dir` under `[package.metadata.vectorctl]` in the migration
+/// crate's manifest, relative to the crate.
+async fn configured_template_dir(migration_dir: &Path) -> Result<Option<PathBuf>> {
+    let manifest = match fs::read_to_string(migration_dir.join(MANIFEST_FILENAME)).await {
+        Ok(manifest) => manifest,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let manifest: toml::Table = toml::from_str(&manifest)?;
+    Ok(manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("vectorctl"))
+        .and_then(|vectorctl| vectorctl.get("template-dir"))
+        .and_then(|dir| dir.as_str())
+        .map(|dir| migration_dir.join(dir)))
+}
+
+/// Registers extra handlebars helpers on the registry templates render with.
+pub type HelperHook<'a> = &'a dyn Fn(&mut Registry);
+
+/// The embedded templates overlaid with the user's, `template_dir` taking
+/// precedence over the manifest's `template-dir`, with the helpers of
+/// `helpers`. `None` when there is nothing to add to the embedded registry.
+async fn user_registry(
+    migration_dir: &Path,
+    template_dir: Option<&Path>,
+    helpers: Option<HelperHook<'_>>,
+) -> Result<Option<Registry>> {
+    let template_dir = match template_dir {
+        Some(dir) => Some(dir.to_owned()),
+        None => configured_template_dir(migration_dir).await?,
+    };
+    if template_dir.is_none() && helpers.is_none() {
+        return Ok(None);
+    }
+
+    let mut registry = Registry::new();
+    if let Some(dir) = template_dir {
+        registry = registry.with_template_dir(dir)?;
+    }
+    if let Some(helpers) = helpers {
+        helpers(&mut registry);
+    }
+    Ok(Some(registry))
+}
+
 async fn render_revision(
+    registry: &Registry,
     dir: impl AsRef<Path>,
-    name: &str,
-    down_rev: Option<&str>,
+    filename: &str,
+    down_revs: &[String],
     message: Option<&str>,
 ) -> Result<()> {
     let mut builder = RevisionTemplate::builder();
     builder
         .date(Utc::now().format(DATE_FMT).to_string())
         .revision_id(revision_id()?)
-        .filename(filename(name));
-    if let Some(down_rev) = down_rev {
-        builder.down_revision_id(down_rev);
-    }
+        .filename(filename)
+        .down_revision_ids(down_revs);
     if let Some(message) = message {
         builder.message(message);
     }
-    builder.render(src_dir(dir))?;
+    builder.render_with(registry, src_dir(dir))?;
     Ok(())
 }
 
-async fn render_migrator(dir: impl AsRef<Path>) -> Result<()> {
+async fn render_migrator(registry: &Registry, dir: impl AsRef<Path>) -> Result<()> {
     let source_dir = src_dir(dir);
     let stems: Vec<String> = ReadDirStream::new(fs::read_dir(&source_dir).await?)
         .filter_map(|item| {
@@ -94,11 +163,131 @@ async fn render_migrator(dir: impl AsRef<Path>) -> Result<()> {
 
     MigratorTemplate::builder()
         .imports(stems)
-        .render(source_dir)?;
+        .render_with(registry, source_dir)?;
 
     Ok(())
 }
 
+/// Byte offset of a span location in `source`.
+fn offset(source: &str, location: LineColumn) -> usize {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(location.line - 1)
+        .map(str::len)
+        .sum();
+    source[line_start..]
+        .char_indices()
+        .nth(location.column)
+        .map_or(source.len(), |(index, _)| line_start + index)
+}
+
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let line = &source[line_start..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// The `vec![...]` returned by `migrations()` in the `MigratorTrait` impl.
+fn migration_list(file: &syn::File) -> Option<&syn::Macro> {
+    file.items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Impl(item) => Some(item),
+            _ => None,
+        })
+        .filter(|item| {
+            item.trait_.as_ref().is_some_and(|(_, path, _)| {
+                path.segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == "MigratorTrait")
+            })
+        })
+        .flat_map(|item| &item.items)
+        .find_map(|item| match item {
+            syn::ImplItem::Fn(function) if function.sig.ident == "migrations" => {
+                match function.block.stmts.last()? {
+                    syn::Stmt::Expr(syn::Expr::Macro(expr), None) => Some(&expr.mac),
+                    syn::Stmt::Macro(stmt) if stmt.semi_token.is_none() => Some(&stmt.mac),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+        .filter(|mac| mac.path.is_ident("vec"))
+}
+
+/// Whether the migrator discovers its revisions with `migrations!`.
+fn discovers_revisions(file: &syn::File) -> bool {
+    file.items.iter().any(|item| match item {
+        syn::Item::Macro(item) => item
+            .mac
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "migrations"),
+        _ => false,
+    })
+}
+
+/// Adds the `mod` declaration and the `migrations()` entry of the revision
+/// `stem` to the migrator source, leaving everything else untouched. A
+/// migrator using `migrations!` is returned as is.
+fn register_revision(source: &str, stem: &str, path: &Path) -> Result<String> {
+    let file = syn::parse_file(source)?;
+    if discovers_revisions(&file) {
+        return Ok(source.to_owned());
+    }
+
+    let last_mod = file
+        .items
+        .iter()
+        .rev()
+        .find_map(|item| match item {
+            syn::Item::Mod(item) if item.ident.to_string().starts_with(REVISION_PREFIX) => {
+                item.semi.as_ref()
+            }
+            _ => None,
+        })
+        .map(|semi| offset(source, semi.span.end()))
+        .ok_or_else(|| MigrateError::MissingModDeclaration(path.to_owned()))?;
+
+    let list =
+        migration_list(&file).ok_or_else(|| MigrateError::MissingMigrationList(path.to_owned()))?;
+    let syn::MacroDelimiter::Bracket(bracket) = &list.delimiter else {
+        return Err(MigrateError::MissingMigrationList(path.to_owned()));
+    };
+    let entry = format!("Box::new({stem}::Migration)");
+    let close = offset(source, bracket.span.close().start());
+    let last_token = list.tokens.clone().into_iter().last();
+    let trailing_comma =
+        matches!(&last_token, Some(TokenTree::Punct(punct)) if punct.as_char() == ',');
+    let (at, insertion) = match last_token {
+        None => (close, entry),
+        Some(token) if token.span().end().line < bracket.span.close().start().line => {
+            let at = offset(source, token.span().end());
+            let indent = line_indent(source, at);
+            if trailing_comma {
+                (at, format!("\n{indent}{entry},"))
+            } else {
+                (at, format!(",\n{indent}{entry}"))
+            }
+        }
+        Some(_) if trailing_comma => (close, format!(" {entry}")),
+        Some(_) => (close, format!(", {entry}")),
+    };
+
+    // the `mod` declarations may come before or after the list, inserting from
+    // the end keeps the offset of the other insertion valid
+    let mut insertions = [(at, insertion), (last_mod, format!("\nmod {stem};"))];
+    insertions.sort_by_key(|(at, _)| std::cmp::Reverse(*at));
+    let mut updated = source.to_owned();
+    for (at, insertion) in insertions {
+        updated.insert_str(at, &insertion);
+    }
+    syn::parse_file(&updated)?;
+    Ok(updated)
+}
+
 #[derive(Debug)]
 struct Backup(PathBuf);
 
@@ -115,11 +304,20 @@ impl Backup {
     }
 }
 
+/// Renders a new migration crate in `migration_dir`, depending on vectorctl at
+/// the release tag of this build. A build made between releases pins the
+/// previous release, so its scaffold may not build until the next one is
+/// tagged.
 pub async fn init(
     pkg: Option<&str>,
     edition: Option<&str>,
     migration_dir: impl AsRef<Path>,
+    template_dir: Option<&Path>,
+    helpers: Option<HelperHook<'_>>,
 ) -> Result<()> {
+    let migration_dir = migration_dir.as_ref();
+    let user_registry = user_registry(migration_dir, template_dir, helpers).await?;
+    let registry = user_registry.as_ref().unwrap_or(&REGISTRY);
     let mut builer = MigrationTemplate::builder();
     if let Some(package_name) = pkg {
         builer.package_name(package_name);
@@ -127,21 +325,160 @@ pub async fn init(
     if let Some(rust_edition) = edition {
         builer.rust_edition(rust_edition);
     }
-    builer.render(&migration_dir)?;
-    render_revision(&migration_dir, "init_migration", None, None).await?;
-    render_migrator(migration_dir).await
+    builer.render_with(registry, migration_dir)?;
+    render_revision(
+        registry,
+        migration_dir,
+        &filename("init_migration"),
+        &[],
+        None,
+    )
+    .await?;
+    render_migrator(registry, migration_dir).await
+}
+
+/// Where `build` caches the migration binary, inside the migration crate.
+pub fn cached_binary(migration_dir: impl AsRef<Path>) -> PathBuf {
+    migration_dir
+        .as_ref()
+        .join(CACHED_BINARY_DIR)
+        .join(format!("migrate{}", std::env::consts::EXE_SUFFIX))
+}
+
+fn newest_modification(path: &Path) -> std::io::Result<SystemTime> {
+    let metadata = std::fs::metadata(path)?;
+    let mut newest = metadata.modified()?;
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            newest = newest.max(newest_modification(&entry?.path())?);
+        }
+    }
+    Ok(newest)
+}
+
+pub(super) fn cached_inputs(migration_dir: &Path) -> PathBuf {
+    migration_dir
+        .join(CACHED_BINARY_DIR)
+        .join(CACHED_INPUTS_FILENAME)
+}
+
+/// The files listed by the dep-info file cargo writes next to an executable,
+/// which include the sources of path dependencies.
+fn dep_info_inputs(dep_info: &str) -> Vec<PathBuf> {
+    dep_info
+        .lines()
+        .filter_map(|line| line.split_once(": ").map(|(_, inputs)| inputs))
+        .flat_map(|inputs| {
+            let mut paths = vec![String::new()];
+            let mut chars = inputs.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => paths.last_mut().unwrap().extend(chars.next()),
+                    ' ' => paths.push(String::new()),
+                    c => paths.last_mut().unwrap().push(c),
+                }
+            }
+            paths
+        })
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The binary cached by `build`, unless the manifest, the lockfile or a file
+/// cargo compiled it from changed since, as after `generate`, `cargo update` or
+/// an edit to a path dependency.
+pub fn fresh_cached_binary(migration_dir: impl AsRef<Path>) -> Option<PathBuf> {
+    let migration_dir = migration_dir.as_ref();
+    let binary = cached_binary(migration_dir);
+    let built_at = std::fs::metadata(&binary).ok()?.modified().ok()?;
+    for source in [
+        migration_dir.join(MANIFEST_FILENAME),
+        migration_dir.join(LOCKFILE_FILENAME),
+        migration_dir.join("src"),
+    ] {
+        if source.exists() && newest_modification(&source).ok()? > built_at {
+            return None;
+        }
+    }
+    // a recorded input that is gone was moved or deleted, which is a change too
+    let inputs = std::fs::read_to_string(cached_inputs(migration_dir)).ok()?;
+    for input in inputs.lines().map(Path::new) {
+        if newest_modification(input).ok()? > built_at {
+            return None;
+        }
+    }
+    Some(binary)
+}
+
+fn find_executable(messages: &str) -> Result<Option<PathBuf>> {
+    let mut executable = None;
+    for line in messages.lines().filter(|line| line.starts_with('{')) {
+        let message: serde_json::Value = serde_json::from_str(line)?;
+        if message["reason"] == "compiler-artifact"
+            && let Some(path) = message["executable"].as_str()
+        {
+            executable = Some(PathBuf::from(path));
+        }
+    }
+    Ok(executable)
+}
+
+/// Builds the migration crate in release mode and caches its binary so later
+/// runs do not need a toolchain.
+pub async fn build(migration_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let migration_dir = migration_dir.as_ref();
+    let output = Command::new("cargo")
+        .arg("build")
+        .arg("--release")
+        .arg("--message-format=json-render-diagnostics")
+        .arg("--manifest-path")
+        .arg(migration_dir.join("Cargo.toml"))
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(MigrateError::Build(output.status));
+    }
+
+    let executable = find_executable(&String::from_utf8_lossy(&output.stdout))?
+        .ok_or(MigrateError::MissingBinary)?;
+    let inputs = match fs::read_to_string(executable.with_extension("d")).await {
+        Ok(dep_info) => dep_info_inputs(&dep_info),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let cached = cached_binary(migration_dir);
+    if let Some(parent) = cached.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::copy(executable, &cached).await?;
+    let inputs = inputs
+        .iter()
+        .map(|input| input.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(cached_inputs(migration_dir), inputs).await?;
+    Ok(cached)
 }
 
 pub async fn create_new_revision(
     migration_dir: impl AsRef<Path>,
     name: &str,
-    down_rev: &str,
+    down_revs: &[String],
     message: Option<&str>,
+    template_dir: Option<&Path>,
+    helpers: Option<HelperHook<'_>>,
 ) -> Result<()> {
-    let migrator = src_dir(&migration_dir).join(MIGRATOR_FILENAME);
+    let migration_dir = migration_dir.as_ref();
+    let user_registry = user_registry(migration_dir, template_dir, helpers).await?;
+    let registry = user_registry.as_ref().unwrap_or(&REGISTRY);
+    let migrator = src_dir(migration_dir).join(MIGRATOR_FILENAME);
+    let stem = filename(name);
+    let source = register_revision(&fs::read_to_string(&migrator).await?, &stem, &migrator)?;
     let backup = Backup::new(&migrator).await?;
-    render_revision(&migration_dir, name, Some(down_rev), message).await?;
-    render_migrator(migration_dir).await?;
+    render_revision(registry, migration_dir, &stem, down_revs, message).await?;
+    // written even when unchanged, so that cargo expands `migrations!` again
+    fs::write(&migrator, source).await?;
     backup.commit().await
 }
 
@@ -176,7 +513,9 @@ mod tests {
     #[tokio::test]
     async fn init_creates_scaffold_and_revision() {
         let tmp = tempdir().unwrap();
-        init(Some("pkg"), Some("2021"), tmp.path()).await.unwrap();
+        init(Some("pkg"), Some("2021"), tmp.path(), None, None)
+            .await
+            .unwrap();
         assert!(tmp.path().join("Cargo.toml").exists());
         let revs: Vec<_> =
             ReadDirStream::new(tokio::fs::read_dir(src_dir(tmp.path())).await.unwrap())
@@ -188,10 +527,17 @@ mod tests {
     #[tokio::test]
     async fn backup_is_removed() {
         let tmp = tempdir().unwrap();
-        init(None, None, tmp.path()).await.unwrap();
-        create_new_revision(tmp.path(), "add_tbl", "prev", Some("msg"))
-            .await
-            .unwrap();
+        init(None, None, tmp.path(), None, None).await.unwrap();
+        create_new_revision(
+            tmp.path(),
+            "add_tbl",
+            &["prev".into()],
+            Some("msg"),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
         assert!(
             !tmp.path()
                 .join("src")
@@ -200,6 +546,326 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn merge_revision_lists_every_parent() {
+        let tmp = tempdir().unwrap();
+        init(None, None, tmp.path(), None, None).await.unwrap();
+        create_new_revision(
+            tmp.path(),
+            "merge_heads",
+            &["left".into(), "right".into()],
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let merge = std::fs::read_dir(tmp.path().join("src"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().ends_with("_merge_heads.rs"))
+            .expect("merge revision should be rendered");
+        let source = std::fs::read_to_string(merge).unwrap();
+        assert!(source.contains(r#"down_revision: &["left", "right"],"#));
+    }
+
+    #[tokio::test]
+    async fn configured_templates_override_the_embedded_ones() {
+        let tmp = tempdir().unwrap();
+        init(None, None, tmp.path(), None, None).await.unwrap();
+        let templates = tmp.path().join("templates").join("revision");
+        std::fs::create_dir_all(&templates).unwrap();
+        std::fs::write(
+            templates.join("{{filename}}.rs.hbs"),
+            "// {{revision_id}} from {{#each down_revision_ids}}{{this}}{{/each}}\n",
+        )
+        .unwrap();
+        let manifest = tmp.path().join(MANIFEST_FILENAME);
+        let mut contents = std::fs::read_to_string(&manifest).unwrap();
+        contents.push_str("\n[package.metadata.vectorctl]\ntemplate-dir = \"templates\"\n");
+        std::fs::write(&manifest, contents).unwrap();
+
+        create_new_revision(tmp.path(), "custom", &["prev".into()], None, None, None)
+            .await
+            .unwrap();
+
+        let revision = std::fs::read_dir(tmp.path().join("src"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().ends_with("_custom.rs"))
+            .expect("custom revision should be rendered");
+        let source = std::fs::read_to_string(revision).unwrap();
+        assert!(source.starts_with("// "));
+        assert!(source.ends_with(" from prev\n"));
+    }
+
+    #[tokio::test]
+    async fn templates_can_use_registered_helpers() {
+        vectorctl_template::handlebars::handlebars_helper!(shout: |text: str| text.to_uppercase());
+
+        let tmp = tempdir().unwrap();
+        init(None, None, tmp.path(), None, None).await.unwrap();
+        let templates = tmp.path().join("templates");
+        std::fs::create_dir_all(templates.join("revision")).unwrap();
+        std::fs::write(
+            templates.join("revision").join("{{filename}}.rs.hbs"),
+            "// {{shout filename}}\n",
+        )
+        .unwrap();
+
+        create_new_revision(
+            tmp.path(),
+            "loud",
+            &[],
+            None,
+            Some(&templates),
+            Some(&|registry: &mut Registry| registry.register_helper("shout", shout)),
+        )
+        .await
+        .unwrap();
+
+        let revision = std::fs::read_dir(tmp.path().join("src"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().ends_with("_loud.rs"))
+            .expect("revision should be rendered");
+        let source = std::fs::read_to_string(revision).unwrap();
+        assert!(source.starts_with("// VERSION_"));
+        assert!(source.ends_with("_LOUD\n"));
+    }
+
+    #[tokio::test]
+    async fn missing_template_dir_is_an_error() {
+        let tmp = tempdir().unwrap();
+        let missing = tmp.path().join("missing");
+        let err = init(None, None, tmp.path(), Some(&missing), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MigrateError::Render(_)));
+    }
+
+    #[test]
+    fn revision_is_added_to_a_formatted_migrator() {
+        let source = r#"mod helpers;
+mod version_1_init;
+mod version_2_users;
+
+pub use helpers::Embedder;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl vectorctl::MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn vectorctl::MigrationTrait>> {
+        vec![
+            Box::new(version_1_init::Migration),
+            Box::new(version_2_users::Migration),
+        ]
+    }
+}
+"#;
+        let updated = register_revision(source, "version_3_posts", Path::new("lib.rs")).unwrap();
+        assert_eq!(
+            updated,
+            r#"mod helpers;
+mod version_1_init;
+mod version_2_users;
+mod version_3_posts;
+
+pub use helpers::Embedder;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl vectorctl::MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn vectorctl::MigrationTrait>> {
+        vec![
+            Box::new(version_1_init::Migration),
+            Box::new(version_2_users::Migration),
+            Box::new(version_3_posts::Migration),
+        ]
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn revision_is_added_when_the_mods_follow_the_migrator() {
+        let source = r#"pub struct Migrator;
+
+impl vectorctl::MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn vectorctl::MigrationTrait>> {
+        vec![Box::new(version_1_init::Migration)]
+    }
+}
+
+mod version_1_init;
+"#;
+        let updated = register_revision(source, "version_2_users", Path::new("lib.rs")).unwrap();
+        assert_eq!(
+            updated,
+            r#"pub struct Migrator;
+
+impl vectorctl::MigratorTrait for Migrator {
+    fn migrations() -> Vec<Box<dyn vectorctl::MigrationTrait>> {
+        vec![Box::new(version_1_init::Migration), Box::new(version_2_users::Migration)]
+    }
+}
+
+mod version_1_init;
+mod version_2_users;
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn generate_keeps_custom_code_in_the_migrator() {
+        let tmp = tempdir().unwrap();
+        init(None, None, tmp.path(), None, None).await.unwrap();
+        let migrator = tmp.path().join("src").join(MIGRATOR_FILENAME);
+        let mut source = std::fs::read_to_string(&migrator).unwrap();
+        source.push_str("\npub fn shared_helper() -> u32 {\n    42\n}\n");
+        std::fs::write(&migrator, source).unwrap();
+
+        create_new_revision(tmp.path(), "add_tbl", &[], None, None, None)
+            .await
+            .unwrap();
+
+        let source = std::fs::read_to_string(&migrator).unwrap();
+        syn::parse_file(&source).unwrap();
+        assert!(source.contains("pub fn shared_helper() -> u32 {\n    42\n}\n"));
+        let stem = Regex::new(r"mod (version_\d+_\d+_add_tbl);")
+            .unwrap()
+            .captures(&source)
+            .expect("the revision module should be declared")[1]
+            .to_owned();
+        assert!(source.contains(&format!(", Box::new({stem}::Migration)]")));
+    }
+
+    #[test]
+    fn migrator_using_the_macro_is_left_as_is() {
+        let source = "vectorctl::migrations!(\"src\");\n\npub fn shared_helper() {}\n";
+        assert_eq!(
+            register_revision(source, "version_3_posts", Path::new("lib.rs")).unwrap(),
+            source
+        );
+    }
+
+    #[tokio::test]
+    async fn generate_fails_without_a_migration_list() {
+        let tmp = tempdir().unwrap();
+        init(None, None, tmp.path(), None, None).await.unwrap();
+        let source_dir = tmp.path().join("src");
+        let migrator = source_dir.join(MIGRATOR_FILENAME);
+        let source = std::fs::read_to_string(&migrator)
+            .unwrap()
+            .replace("vec![", "Vec::from([")
+            .replace("::Migration)]", "::Migration)])");
+        std::fs::write(&migrator, &source).unwrap();
+        let files = std::fs::read_dir(&source_dir).unwrap().count();
+
+        let err = create_new_revision(tmp.path(), "add_tbl", &[], None, None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MigrateError::MissingMigrationList(_)));
+        assert_eq!(std::fs::read_to_string(&migrator).unwrap(), source);
+        assert_eq!(std::fs::read_dir(&source_dir).unwrap().count(), files);
+    }
+
+    #[test]
+    fn cached_binary_is_stale_once_a_source_changes() {
+        let tmp = tempdir().unwrap();
+        let source_dir = tmp.path().join("src");
+        std::fs::create_dir_all(&source_dir).unwrap();
+        std::fs::write(tmp.path().join(MANIFEST_FILENAME), "").unwrap();
+        std::fs::write(source_dir.join(MIGRATOR_FILENAME), "").unwrap();
+        assert_eq!(fresh_cached_binary(tmp.path()), None);
+
+        let binary = cached_binary(tmp.path());
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        let built_at = SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::create(&binary)
+            .unwrap()
+            .set_modified(built_at)
+            .unwrap();
+        std::fs::write(cached_inputs(tmp.path()), "").unwrap();
+        assert_eq!(fresh_cached_binary(tmp.path()), Some(binary));
+
+        std::fs::File::create(source_dir.join("version_2_new.rs"))
+            .unwrap()
+            .set_modified(built_at + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(fresh_cached_binary(tmp.path()), None);
+    }
+
+    #[test]
+    fn cached_binary_is_stale_once_the_lockfile_or_a_dependency_changes() {
+        let tmp = tempdir().unwrap();
+        let dependency = tmp.path().join("vectorctl").join("lib.rs");
+        std::fs::create_dir_all(dependency.parent().unwrap()).unwrap();
+        std::fs::write(&dependency, "").unwrap();
+        let migration_dir = tmp.path().join("migration");
+        let binary = cached_binary(&migration_dir);
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        let built_at = SystemTime::now() + std::time::Duration::from_secs(60);
+        std::fs::File::create(&binary)
+            .unwrap()
+            .set_modified(built_at)
+            .unwrap();
+        // a binary built before the inputs were recorded is not trusted
+        assert_eq!(fresh_cached_binary(&migration_dir), None);
+
+        std::fs::write(cached_inputs(&migration_dir), dependency.to_str().unwrap()).unwrap();
+        assert_eq!(fresh_cached_binary(&migration_dir), Some(binary.clone()));
+
+        let later = built_at + std::time::Duration::from_secs(1);
+        std::fs::File::create(&dependency)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(fresh_cached_binary(&migration_dir), None);
+
+        std::fs::remove_file(&dependency).unwrap();
+        assert_eq!(fresh_cached_binary(&migration_dir), None);
+
+        std::fs::write(cached_inputs(&migration_dir), "").unwrap();
+        assert_eq!(fresh_cached_binary(&migration_dir), Some(binary));
+        std::fs::File::create(migration_dir.join(LOCKFILE_FILENAME))
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(fresh_cached_binary(&migration_dir), None);
+    }
+
+    #[test]
+    fn inputs_are_read_from_the_dep_info() {
+        let dep_info = "/tmp/target/release/migration: /work/migration/src/main.rs /work/my\\ deps/lib.rs\n\n/work/migration/src/main.rs:\n";
+        assert_eq!(
+            dep_info_inputs(dep_info),
+            [
+                PathBuf::from("/work/migration/src/main.rs"),
+                PathBuf::from("/work/my deps/lib.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn executable_is_read_from_cargo_messages() {
+        let messages = [
+            r#"{"reason":"compiler-artifact","target":{"name":"migration"},"executable":null}"#,
+            r#"{"reason":"compiler-artifact","target":{"name":"migration"},"executable":"/tmp/target/release/migration"}"#,
+            r#"{"reason":"build-finished","success":true}"#,
+        ]
+        .join("\n");
+        assert_eq!(
+            find_executable(&messages).unwrap(),
+            Some(PathBuf::from("/tmp/target/release/migration"))
+        );
+        assert_eq!(find_executable("").unwrap(), None);
+    }
+
     proptest! {
         #[test]
         fn prop_base64(buf in any::<[u8;8]>()) {
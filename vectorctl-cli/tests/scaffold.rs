@@ -0,0 +1,97 @@
+//! Builds the crate generated by `vectorctl migrate init` against this checkout
+//! rather than the git tag its manifest pins.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::tempdir;
+use vectorctl_cli::commands::{create_new_revision, init};
+
+/// Version of the `vectorctl` crate in this checkout, the one being shipped.
+fn shipped_version(checkout: &Path) -> String {
+    let manifest: toml::Table =
+        toml::from_str(&fs::read_to_string(checkout.join("Cargo.toml")).unwrap()).unwrap();
+    manifest["package"]["version"].as_str().unwrap().to_owned()
+}
+
+/// Points the scaffold in `migration_dir` at this checkout and returns its
+/// manifest. The scaffold must pin the release tag of the version being
+/// shipped, which the checkout stands in for: the tag of an unreleased version
+/// only exists once `cog bump` has run, and until then the scaffold does not
+/// build against it.
+fn use_checkout(migration_dir: &Path) -> PathBuf {
+    let checkout = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let version = shipped_version(checkout);
+    let manifest_path = migration_dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+    let pinned = format!("tag = \"v{version}\"");
+    assert!(
+        manifest.lines().any(|line| line == pinned),
+        "vectorctl must be pinned with `{pinned}`:\n{manifest}"
+    );
+
+    // a `[patch]` section would still fetch the git source, so the dependency
+    // is pointed at the checkout directly, requiring the pinned version
+    let manifest = manifest
+        .lines()
+        .map(|line| {
+            if line.starts_with("git = ") {
+                format!("path = {checkout:?}")
+            } else if line == pinned {
+                format!("version = \"={version}\"")
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(&manifest_path, manifest).unwrap();
+    // resolve the versions this workspace is tested with
+    fs::copy(
+        checkout.join("Cargo.lock"),
+        migration_dir.join("Cargo.lock"),
+    )
+    .unwrap();
+    manifest_path
+}
+
+fn build(manifest_path: &Path) {
+    let status = Command::new(env!("CARGO"))
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        // shared between runs so that only the scaffold itself is rebuilt
+        .env(
+            "CARGO_TARGET_DIR",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("scaffold"),
+        )
+        .status()
+        .unwrap();
+    assert!(status.success(), "the scaffold failed to build: {status}");
+}
+
+#[tokio::test]
+async fn init_scaffold_compiles() {
+    let tmp = tempdir().unwrap();
+    let migration_dir = tmp.path().join("migration");
+    init(None, None, &migration_dir, None, None).await.unwrap();
+    build(&use_checkout(&migration_dir));
+}
+
+#[tokio::test]
+async fn migrations_macro_scaffold_compiles() {
+    let tmp = tempdir().unwrap();
+    let migration_dir = tmp.path().join("migration");
+    init(None, None, &migration_dir, None, None).await.unwrap();
+    let migrator = migration_dir.join("src").join("lib.rs");
+    let source = "vectorctl::migrations!(\"src\");\n";
+    fs::write(&migrator, source).unwrap();
+
+    create_new_revision(&migration_dir, "add_tbl", &[], None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&migrator).unwrap(), source);
+    build(&use_checkout(&migration_dir));
+}
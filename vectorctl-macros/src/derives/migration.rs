@@ -1,19 +1,27 @@
 use proc_macro2::TokenStream;
 use quote::quote;
+use sha2::{Digest, Sha256};
+use std::path::Path;
 
 struct DeriveMigrationMeta {
     ident: syn::Ident,
+    checksum: Option<String>,
 }
 
 impl DeriveMigrationMeta {
-    fn new(input: syn::DeriveInput) -> Self {
+    fn new(input: syn::DeriveInput, source: Option<&Path>) -> Self {
         let ident = input.ident;
+        let checksum = source.and_then(checksum);
 
-        DeriveMigrationMeta { ident }
+        DeriveMigrationMeta { ident, checksum }
     }
 
     fn expand(&self) -> TokenStream {
         let ident = &self.ident;
+        let checksum = match &self.checksum {
+            Some(checksum) => quote!(Some(#checksum.into())),
+            None => quote!(None),
+        };
 
         quote!(
             #[automatically_derived]
@@ -25,11 +33,30 @@ impl DeriveMigrationMeta {
                 fn revision(&self) -> vectorctl::Revision {
                     REVISION
                 }
+
+                fn checksum(&self) -> Option<String> {
+                    #checksum
+                }
             }
         )
     }
 }
 
-pub fn expand_derive_migration_meta(input: syn::DeriveInput) -> syn::Result<TokenStream> {
-    Ok(DeriveMigrationMeta::new(input).expand())
+/// SHA-256 of the migration source, with line endings normalized so that the
+/// same file hashes alike on every platform.
+fn checksum(path: &Path) -> Option<String> {
+    let source = std::fs::read_to_string(path).ok()?;
+    Some(
+        Sha256::digest(source.replace("\r\n", "\n").as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    )
+}
+
+pub fn expand_derive_migration_meta(
+    input: syn::DeriveInput,
+    source: Option<&Path>,
+) -> syn::Result<TokenStream> {
+    Ok(DeriveMigrationMeta::new(input, source).expand())
 }
@@ -4,11 +4,41 @@ use proc_macro::TokenStream;
 use syn::{DeriveInput, Error, parse_macro_input};
 
 mod derives;
+mod migrations;
 
 #[proc_macro_derive(DeriveMigrationMeta)]
 pub fn derive_migration_meta(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    derives::expand_derive_migration_meta(input)
+    let source = proc_macro::Span::call_site().local_file();
+    derives::expand_derive_migration_meta(input, source.as_deref())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Declares a module for every `version_*.rs` revision in a directory,
+/// relative to the crate root, and a struct implementing `MigratorTrait` over
+/// them, named `Migrator` unless given.
+///
+/// ```ignore
+/// vectorctl::migrations!("src");
+/// ```
+///
+/// Revisions are discovered when the crate compiles, and cargo does not
+/// watch the directory for new files: one added by `generate` is picked up
+/// because it rewrites the file invoking the macro, but one added by hand or
+/// by a `git pull` is not. A build script makes cargo watch the directory:
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     println!("cargo::rerun-if-changed=src");
+/// }
+/// ```
+#[proc_macro]
+pub fn migrations(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as migrations::Migrations);
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+    migrations::expand_migrations(input, std::path::Path::new(&manifest_dir))
         .unwrap_or_else(Error::into_compile_error)
         .into()
 }
@@ -0,0 +1,164 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::path::{Path, PathBuf};
+use syn::{
+    Error, Ident, LitStr, Token,
+    parse::{Parse, ParseStream},
+};
+
+const REVISION_PREFIX: &str = "version";
+const DEFAULT_MIGRATOR: &str = "Migrator";
+
+/// `migrations!("src")` or `migrations!("src", CustomMigrator)`.
+pub struct Migrations {
+    dir: LitStr,
+    ident: Ident,
+}
+
+impl Parse for Migrations {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dir = input.parse()?;
+        let ident = if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let ident = input.parse()?;
+            input.parse::<Option<Token![,]>>()?;
+            ident
+        } else {
+            Ident::new(DEFAULT_MIGRATOR, Span::call_site())
+        };
+        Ok(Migrations { dir, ident })
+    }
+}
+
+/// Revision files in `dir` as `(module name, path)`, sorted by name.
+fn revisions(dir: &Path) -> std::io::Result<Vec<(String, PathBuf)>> {
+    let mut revisions = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with(REVISION_PREFIX) && name.ends_with(".rs") && path.is_file() {
+            revisions.push((name.trim_end_matches(".rs").to_owned(), path));
+        }
+    }
+    revisions.sort();
+    Ok(revisions)
+}
+
+/// Expands `input` with its directory relative to `manifest_dir`.
+pub fn expand_migrations(input: Migrations, manifest_dir: &Path) -> syn::Result<TokenStream> {
+    let span = input.dir.span();
+    let dir = manifest_dir.join(input.dir.value());
+    let revisions = revisions(&dir)
+        .map_err(|err| Error::new(span, format!("cannot read {}: {err}", dir.display())))?;
+    if revisions.is_empty() {
+        return Err(Error::new(
+            span,
+            format!("no {REVISION_PREFIX}_*.rs revision in {}", dir.display()),
+        ));
+    }
+
+    let mut modules = Vec::with_capacity(revisions.len());
+    let mut paths = Vec::with_capacity(revisions.len());
+    for (name, path) in &revisions {
+        modules.push(
+            syn::parse_str::<Ident>(name)
+                .map_err(|_| Error::new(span, format!("`{name}` is not a valid module name")))?,
+        );
+        paths.push(
+            path.to_str().ok_or_else(|| {
+                Error::new(span, format!("{} is not valid UTF-8", path.display()))
+            })?,
+        );
+    }
+    let ident = &input.ident;
+
+    Ok(quote!(
+        #(
+            #[path = #paths]
+            mod #modules;
+        )*
+
+        pub struct #ident;
+
+        impl vectorctl::MigratorTrait for #ident {
+            fn migrations() -> Vec<Box<dyn vectorctl::MigrationTrait>> {
+                vec![#(Box::new(#modules::Migration)),*]
+            }
+
+            fn version() -> Option<&'static str> {
+                Some(env!("CARGO_PKG_VERSION"))
+            }
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn expand(input: &str, manifest_dir: &Path) -> syn::Result<syn::File> {
+        let input = syn::parse_str::<Migrations>(input)?;
+        syn::parse2(expand_migrations(input, manifest_dir)?)
+    }
+
+    #[test]
+    fn test_declares_every_revision_and_the_migrator() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        fs::create_dir(&src).unwrap();
+        for file in [
+            "version_2_b.rs",
+            "version_1_a.rs",
+            "lib.rs",
+            "version_notes.md",
+        ] {
+            fs::write(src.join(file), "").unwrap();
+        }
+
+        let file = expand(r#""src", CustomMigrator"#, tmp.path()).unwrap();
+        let modules = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Mod(item) => Some(item),
+                _ => None,
+            })
+            .map(|item| {
+                let syn::Meta::NameValue(path) = &item.attrs[0].meta else {
+                    panic!("expected a #[path] attribute");
+                };
+                assert!(path.path.is_ident("path"));
+                item.ident.to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(modules, ["version_1_a", "version_2_b"]);
+
+        let syn::Item::Impl(migrator) = file.items.last().unwrap() else {
+            panic!("expected the MigratorTrait impl last");
+        };
+        assert!(migrator.attrs.is_empty());
+        let syn::Type::Path(self_ty) = migrator.self_ty.as_ref() else {
+            panic!("expected a named migrator");
+        };
+        assert!(self_ty.path.is_ident("CustomMigrator"));
+    }
+
+    #[test]
+    fn test_defaults_to_migrator_and_rejects_an_empty_directory() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("version_1_a.rs"), "").unwrap();
+        let file = expand(r#"".""#, tmp.path()).unwrap();
+        assert!(file.items.iter().any(|item| matches!(
+            item,
+            syn::Item::Struct(item) if item.ident == DEFAULT_MIGRATOR
+        )));
+
+        fs::create_dir(tmp.path().join("empty")).unwrap();
+        let Err(err) = expand(r#""empty""#, tmp.path()) else {
+            panic!("an empty directory should be rejected");
+        };
+        assert!(err.to_string().starts_with("no version_*.rs revision in"));
+    }
+}
@@ -1,11 +1,17 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use clap::Parser;
 use thiserror::Error;
-use vectorctl_backend::generic::VectorBackendError;
-use vectorctl_cli::commands::{MigrateError, MigrateSubcommands, create_new_revision, init};
+use vectorctl_backend::generic::{LedgerOptions, VectorBackendError};
+use vectorctl_cli::commands::{
+    LedgerArgs, MigrateError, MigrateSubcommands, OutputFormat, build, create_new_revision, init,
+};
 
-use crate::migrator::{MigrationError, MigratorTrait};
+use crate::{
+    migrator::{Direction, MigrationError, MigratorTrait},
+    presenter::Presenter,
+    report::RunReport,
+};
 
 #[derive(Error, Debug)]
 pub enum CliError {
@@ -17,6 +23,14 @@ pub enum CliError {
     Context(#[from] crate::context::ContextError),
     #[error(transparent)]
     Backend(#[from] VectorBackendError),
+    #[error(
+        "{option} {requested} does not match the ledger of the migrator ({actual:?}), build its context with `.with_ledger(cli.ledger_options())`"
+    )]
+    LedgerMismatch {
+        option: &'static str,
+        requested: String,
+        actual: Option<String>,
+    },
 }
 
 #[derive(Parser)]
@@ -27,7 +41,7 @@ pub struct Cli {
         short = 'u',
         long,
         env = "DATABASE_URL",
-        help = "vector database URL",
+        help = "vector database URL, its scheme selects the backend",
         default_value = "http://localhost:6334"
     )]
     pub database_url: String,
@@ -59,45 +73,250 @@ pub struct Cli {
     )]
     pub sql_database_url: Option<String>,
 
+    #[arg(
+        global = true,
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "output format of status and run reports"
+    )]
+    pub format: OutputFormat,
+
+    #[command(flatten)]
+    pub ledger: LedgerArgs,
+
     #[command(subcommand)]
     pub command: Option<MigrateSubcommands>,
 }
 
+impl Cli {
+    /// Ledger collection, namespace and lock lease given by `--ledger-name`,
+    /// `--ledger-namespace` and `--lock-lease`, to pass to [`crate::Context::with_ledger`].
+    /// [`run_migrate`] refuses to touch a ledger the context was not built for.
+    pub fn ledger_options(&self) -> LedgerOptions {
+        LedgerOptions {
+            name: self.ledger.ledger_name.clone(),
+            namespace: self.ledger.ledger_namespace.clone(),
+            lock_lease: self.ledger.lock_lease.map(Duration::from_secs),
+        }
+    }
+}
+
+/// Refuses a ledger selected by `--ledger-name` or `--ledger-namespace` that
+/// the context does not record in, rather than silently running against
+/// another ledger, and a `--lock-lease` the context would ignore.
+fn check_ledger(requested: &LedgerOptions, context: &LedgerOptions) -> Result<(), CliError> {
+    for (option, requested, actual) in [
+        ("--ledger-name", &requested.name, &context.name),
+        (
+            "--ledger-namespace",
+            &requested.namespace,
+            &context.namespace,
+        ),
+    ] {
+        if let Some(requested) = requested
+            && Some(requested) != actual.as_ref()
+        {
+            return Err(CliError::LedgerMismatch {
+                option,
+                requested: requested.clone(),
+                actual: actual.clone(),
+            });
+        }
+    }
+    if let Some(lease) = requested.lock_lease
+        && Some(lease) != context.lock_lease
+    {
+        return Err(CliError::LedgerMismatch {
+            option: "--lock-lease",
+            requested: format!("{}s", lease.as_secs()),
+            actual: context
+                .lock_lease
+                .map(|lease| format!("{}s", lease.as_secs())),
+        });
+    }
+    Ok(())
+}
+
 pub async fn run_migrate<M>(_: M, context: &crate::context::Context) -> Result<(), CliError>
 where
     M: MigratorTrait,
 {
     let cli = Cli::parse();
+    let touches_ledger = !matches!(
+        cli.command,
+        Some(
+            MigrateSubcommands::Init { .. }
+                | MigrateSubcommands::Build
+                | MigrateSubcommands::Generate { .. }
+                | MigrateSubcommands::Merge { .. }
+        )
+    );
+    if touches_ledger {
+        check_ledger(&cli.ledger_options(), &context.ledger)?;
+    }
 
     let migration_dir = cli.migration_dir;
+    let presenter = Presenter::new(cli.format);
 
     match cli.command {
         Some(MigrateSubcommands::Init {
             package_name,
             rust_edition,
+            templates,
         }) => {
             init(
                 package_name.as_deref(),
                 rust_edition.as_deref(),
                 migration_dir,
+                templates.template_dir.as_deref(),
+                Some(&M::register_helpers),
             )
             .await?
         }
-        Some(MigrateSubcommands::Generate { name, message }) => {
+        Some(MigrateSubcommands::Build) => {
+            let binary = build(migration_dir).await?;
+            println!("Migration binary cached at {}", binary.display());
+        }
+        Some(MigrateSubcommands::Generate {
+            name,
+            message,
+            templates,
+        }) => {
+            let heads = M::heads()?;
+            if heads.len() > 1 {
+                return Err(MigrationError::MultipleHeads(heads).into());
+            }
             create_new_revision(
                 migration_dir,
                 name.as_ref(),
-                M::latest_revision()?.revision().revision,
+                &heads,
                 message.as_deref(),
+                templates.template_dir.as_deref(),
+                Some(&M::register_helpers),
             )
             .await?
         }
-        Some(MigrateSubcommands::Up { to }) => M::up(context, to).await?,
-        Some(MigrateSubcommands::Down { to }) => M::down(context, to).await?,
-        Some(MigrateSubcommands::Refresh) => M::refresh(context).await?,
-        Some(MigrateSubcommands::Reset) => M::reset(context).await?,
-        Some(MigrateSubcommands::Status) => M::status(context).await?,
-        None => M::up(context, None).await?,
+        Some(MigrateSubcommands::Merge {
+            name,
+            message,
+            templates,
+        }) => {
+            let heads = M::heads()?;
+            if heads.len() < 2 {
+                println!("Nothing to merge");
+            } else {
+                create_new_revision(
+                    migration_dir,
+                    name.as_ref(),
+                    &heads,
+                    message.as_deref(),
+                    templates.template_dir.as_deref(),
+                    Some(&M::register_helpers),
+                )
+                .await?
+            }
+        }
+        Some(MigrateSubcommands::Up {
+            to,
+            dry_run: true,
+            allow_drift,
+        }) => presenter.plan(&M::dry_run(context, to, Direction::Up, allow_drift).await?)?,
+        Some(MigrateSubcommands::Up {
+            to, allow_drift, ..
+        }) => present_run(&presenter, M::up(context, to, allow_drift).await)?,
+        Some(MigrateSubcommands::Down { to, dry_run: true }) => {
+            presenter.plan(&M::dry_run(context, to, Direction::Down, false).await?)?
+        }
+        Some(MigrateSubcommands::Down { to, .. }) => {
+            present_run(&presenter, M::down(context, to).await)?
+        }
+        Some(MigrateSubcommands::Refresh { dry_run: true }) => {
+            presenter.plan(&M::dry_run(context, None, Direction::Refresh, false).await?)?
+        }
+        Some(MigrateSubcommands::Refresh { .. }) => {
+            present_run(&presenter, M::refresh(context).await)?
+        }
+        Some(MigrateSubcommands::Reset { dry_run: true }) => {
+            presenter.plan(&M::dry_run(context, None, Direction::Down, false).await?)?
+        }
+        Some(MigrateSubcommands::Reset { .. }) => present_run(&presenter, M::reset(context).await)?,
+        Some(MigrateSubcommands::Status { verbose }) => {
+            presenter.status(&M::status(context).await?, verbose)?
+        }
+        Some(MigrateSubcommands::History) => presenter.history(&M::history(context).await?)?,
+        Some(MigrateSubcommands::Stamp { revision, purge }) => {
+            presenter.plan(&M::stamp(context, &revision, purge).await?)?;
+            presenter.message(&format!("Ledger stamped at {revision}"));
+        }
+        Some(MigrateSubcommands::Unlock) => {
+            M::unlock(context).await?;
+            presenter.message("Migration lock released");
+        }
+        None => present_run(&presenter, M::up(context, None, false).await)?,
     }
     Ok(())
 }
+
+/// Shows what a run executed, including the migrations that ran before one failed.
+fn present_run(
+    presenter: &Presenter,
+    result: Result<RunReport, MigrationError>,
+) -> Result<(), CliError> {
+    match result {
+        Ok(report) => Ok(presenter.run(&report)?),
+        Err(MigrationError::Run { report, source }) => {
+            presenter.run(&report)?;
+            Err(CliError::Migrate(*source))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(name: Option<&str>, namespace: Option<&str>) -> LedgerOptions {
+        LedgerOptions {
+            name: name.map(str::to_owned),
+            namespace: namespace.map(str::to_owned),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ledger_flags_must_match_the_context() {
+        let context = options(Some("ledger"), Some("search"));
+
+        check_ledger(&options(None, None), &context).unwrap();
+        check_ledger(&context, &context).unwrap();
+        assert!(matches!(
+            check_ledger(&options(None, Some("search")), &LedgerOptions::default()),
+            Err(CliError::LedgerMismatch {
+                option: "--ledger-namespace",
+                actual: None,
+                ..
+            })
+        ));
+        assert!(matches!(
+            check_ledger(&options(Some("other"), None), &context),
+            Err(CliError::LedgerMismatch {
+                option: "--ledger-name",
+                ..
+            })
+        ));
+        let lease = LedgerOptions {
+            lock_lease: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        check_ledger(&lease, &lease).unwrap();
+        assert!(matches!(
+            check_ledger(&lease, &LedgerOptions::default()),
+            Err(CliError::LedgerMismatch {
+                option: "--lock-lease",
+                ..
+            })
+        ));
+    }
+}
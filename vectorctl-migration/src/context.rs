@@ -1,15 +1,27 @@
 use fnv::FnvHashMap;
+use futures::future::BoxFuture;
 use std::{
     any::{Any, TypeId},
+    error::Error as StdError,
     fmt::{self, Debug, Formatter},
     ops::Deref,
 };
 use thiserror::Error;
+use tokio::sync::OnceCell;
+use vectorctl_backend::{
+    AnyLedger,
+    generic::{LedgerOptions, VectorBackendError},
+};
 
 #[derive(Debug, Error)]
 pub enum ContextError {
     #[error("Resource: {0}")]
     Resource(String),
+    #[error("Resource `{resource}` failed to initialize: {source}")]
+    Factory {
+        resource: &'static str,
+        source: Box<dyn StdError + Send + Sync>,
+    },
 }
 
 #[derive(Default)]
@@ -28,57 +40,153 @@ impl Resource {
         self.0.insert(TypeId::of::<R>(), Box::new(resource));
     }
 
+    /// Inserts every resource of `resources`. They all have the type `R`, so
+    /// the last one is kept, use [`Resource::insert_boxed`] for different types.
     pub fn insert_many<R: Any + Send + Sync>(&mut self, resources: Vec<R>) {
-        let _ = resources.into_iter().map(|resource| self.insert(resource));
+        for resource in resources {
+            self.insert(resource);
+        }
+    }
+
+    /// Inserts resources of different types at once, each under the type of
+    /// the boxed value.
+    pub fn insert_boxed(
+        &mut self,
+        resources: impl IntoIterator<Item = Box<dyn Any + Send + Sync>>,
+    ) {
+        self.0.extend(
+            resources
+                .into_iter()
+                .map(|resource| ((*resource).type_id(), resource)),
+        );
     }
 }
 
+type Factory = Box<
+    dyn Fn() -> BoxFuture<'static, Result<Box<dyn Any + Send + Sync>, ContextError>> + Send + Sync,
+>;
+
+/// A resource built by its factory on first access. A failed build is retried
+/// on the next access.
+struct LazyResource {
+    factory: Factory,
+    value: OnceCell<Box<dyn Any + Send + Sync>>,
+}
+
 impl Debug for Resource {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_tuple("Resource").finish()
     }
 }
 
-#[cfg(feature = "qdrant-backend")]
-pub type Backend = vectorctl_backend::Qdrant;
+/// Whichever compiled-in backend the database URL scheme selects.
+pub type Backend = vectorctl_backend::AnyBackend;
 
-#[cfg(not(any(feature = "qdrant-backend")))]
+#[cfg(not(any(feature = "qdrant-backend", feature = "in-memory-backend")))]
 compile_error!(
-    "You must enable exactly one vectordb backend feature: \
-     either `qdrant-backend`"
+    "You must enable a vectordb backend feature: \
+     either `qdrant-backend` or `in-memory-backend`"
 );
 
 pub struct Context {
     pub resources: Resource,
     pub backend: Backend,
+    /// ledger collection and namespace the migrator records its history in
+    pub ledger: LedgerOptions,
+    lazy_resources: FnvHashMap<TypeId, LazyResource>,
 }
 
 impl Context {
-    pub fn new(backend: Backend) -> Self {
+    pub fn new(backend: impl Into<Backend>) -> Self {
         Self {
-            backend,
+            backend: backend.into(),
             resources: Resource::default(),
+            ledger: LedgerOptions::default(),
+            lazy_resources: FnvHashMap::default(),
+        }
+    }
+
+    pub fn builder(backend: impl Into<Backend>) -> ContextBuilder {
+        ContextBuilder {
+            context: Self::new(backend),
         }
     }
 
+    pub fn with_ledger(mut self, ledger: LedgerOptions) -> Self {
+        self.ledger = ledger;
+        self
+    }
+
+    /// The ledger selected by the `ledger` options.
+    pub fn ledger(&self) -> AnyLedger {
+        self.backend.ledger(&self.ledger)
+    }
+
+    /// Connects to the backend matching the scheme of `url`.
+    pub fn from_url(url: &str, api_key: Option<String>) -> Result<Self, VectorBackendError> {
+        Ok(Self::new(Backend::new(url, api_key)?))
+    }
+
+    /// The resource of type `R`, as inserted or already built by its factory.
+    ///
+    /// This never runs a factory: until [`Context::lazy_resource`] has built a
+    /// resource registered with [`ContextBuilder::factory`], it fails with
+    /// [`ContextError::Resource`]. Turning an inserted resource into a factory
+    /// therefore breaks the migrations reading it with `resource`; they have
+    /// to switch to `lazy_resource`, or the resource has to be built before
+    /// [`ContextBuilder::build`] and inserted with [`ContextBuilder::resource`].
     pub fn resource<R: Any + Send + Sync>(&self) -> Result<&R, ContextError> {
         self.resource_opt::<R>().ok_or_else(|| {
+            let reason = if self.lazy_resources.contains_key(&TypeId::of::<R>()) {
+                "is built by a factory, access it with `lazy_resource`"
+            } else {
+                "does not exist"
+            };
             ContextError::Resource(format!(
-                "Resource `{}` does not exist.",
+                "Resource `{}` {reason}.",
                 std::any::type_name::<R>()
             ))
         })
     }
 
+    /// Like [`Context::resource`], but panics where that fails, including for
+    /// a factory resource that has not been built yet.
     pub fn resource_unchecked<R: Any + Send + Sync>(&self) -> &R {
-        self.resource_opt::<R>()
-            .unwrap_or_else(|| panic!("Resource `{}` does not exist.", std::any::type_name::<R>()))
+        self.resource::<R>().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// The resource of type `R`, built by its factory on first access when it
+    /// was registered with [`ContextBuilder::factory`].
+    pub async fn lazy_resource<R: Any + Send + Sync>(&self) -> Result<&R, ContextError> {
+        if let Some(resource) = self.resource_opt::<R>() {
+            return Ok(resource);
+        }
+        let Some(lazy) = self.lazy_resources.get(&TypeId::of::<R>()) else {
+            return self.resource::<R>();
+        };
+        lazy.value
+            .get_or_try_init(|| (lazy.factory)())
+            .await?
+            .downcast_ref::<R>()
+            .ok_or_else(|| {
+                ContextError::Resource(format!(
+                    "Resource `{}` has the wrong type.",
+                    std::any::type_name::<R>()
+                ))
+            })
     }
 
+    /// The resource of type `R` if it was inserted or has already been built,
+    /// never runs a factory.
     pub fn resource_opt<R: Any + Send + Sync>(&self) -> Option<&R> {
         self.resources
             .0
             .get(&TypeId::of::<R>())
+            .or_else(|| {
+                self.lazy_resources
+                    .get(&TypeId::of::<R>())
+                    .and_then(|lazy| lazy.value.get())
+            })
             .and_then(|d| d.downcast_ref::<R>())
     }
 
@@ -90,3 +198,169 @@ impl Context {
         self.resources.insert_many(resources)
     }
 }
+
+/// Assembles a [`Context`] from ready resources and async factories. Factories
+/// run on first [`Context::lazy_resource`] access to their resource, so that a
+/// migration run only pays for the clients and connections its migrations use.
+pub struct ContextBuilder {
+    context: Context,
+}
+
+impl ContextBuilder {
+    pub fn ledger(mut self, ledger: LedgerOptions) -> Self {
+        self.context.ledger = ledger;
+        self
+    }
+
+    pub fn resource<R: Any + Send + Sync>(mut self, resource: R) -> Self {
+        self.context.insert_resource(resource);
+        self
+    }
+
+    /// Registers `factory` to build the resource of type `R` on first access,
+    /// such as an embedding client or a database connection. Only
+    /// [`Context::lazy_resource`] runs it, the synchronous accessors see the
+    /// resource once it has been built.
+    pub fn factory<R, E, F, Fut>(mut self, factory: F) -> Self
+    where
+        R: Any + Send + Sync,
+        E: StdError + Send + Sync + 'static,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        let factory: Factory = Box::new(move || {
+            let future = factory();
+            Box::pin(async move {
+                future
+                    .await
+                    .map(|resource| Box::new(resource) as Box<dyn Any + Send + Sync>)
+                    .map_err(|err| ContextError::Factory {
+                        resource: std::any::type_name::<R>(),
+                        source: Box::new(err),
+                    })
+            })
+        });
+        self.context.lazy_resources.insert(
+            TypeId::of::<R>(),
+            LazyResource {
+                factory,
+                value: OnceCell::new(),
+            },
+        );
+        self
+    }
+
+    pub fn build(self) -> Context {
+        self.context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Config(&'static str);
+
+    #[derive(Debug)]
+    struct Client(usize);
+
+    fn context() -> ContextBuilder {
+        Context::builder(vectorctl_backend::Memory::default())
+    }
+
+    #[test]
+    fn test_insert_many_inserts_resources() {
+        let mut resources = Resource::default();
+        resources.insert_many(vec![Config("a"), Config("b")]);
+        assert_eq!(
+            resources[&TypeId::of::<Config>()].downcast_ref::<Config>(),
+            Some(&Config("b"))
+        );
+
+        let mut resources = Resource::default();
+        resources.insert_boxed([
+            Box::new(Config("a")) as Box<dyn Any + Send + Sync>,
+            Box::new(42_u32),
+        ]);
+
+        assert_eq!(resources.len(), 2);
+        assert_eq!(
+            resources[&TypeId::of::<Config>()].downcast_ref::<Config>(),
+            Some(&Config("a"))
+        );
+        assert_eq!(
+            resources[&TypeId::of::<u32>()].downcast_ref::<u32>(),
+            Some(&42)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_factories_run_once_on_first_access() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ctx =
+            context()
+                .resource(Config("ready"))
+                .factory({
+                    let calls = Arc::clone(&calls);
+                    move || {
+                        let calls = Arc::clone(&calls);
+                        async move {
+                            Ok::<_, std::io::Error>(Client(calls.fetch_add(1, Ordering::SeqCst)))
+                        }
+                    }
+                })
+                .build();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(ctx.resource_opt::<Client>().is_none());
+        assert!(matches!(
+            ctx.resource::<Client>(),
+            Err(ContextError::Resource(message)) if message.contains("lazy_resource")
+        ));
+
+        assert_eq!(ctx.lazy_resource::<Client>().await.unwrap().0, 0);
+        assert_eq!(ctx.lazy_resource::<Client>().await.unwrap().0, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(ctx.resource::<Client>().unwrap().0, 0);
+        assert_eq!(ctx.resource::<Config>().unwrap(), &Config("ready"));
+        assert_eq!(
+            ctx.lazy_resource::<Config>().await.unwrap(),
+            &Config("ready")
+        );
+        assert!(matches!(
+            ctx.lazy_resource::<u32>().await,
+            Err(ContextError::Resource(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_factory_is_retried() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ctx = context()
+            .factory({
+                let calls = Arc::clone(&calls);
+                move || {
+                    let attempt = calls.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        if attempt == 0 {
+                            Err(std::io::Error::other("unreachable"))
+                        } else {
+                            Ok(Client(attempt))
+                        }
+                    }
+                }
+            })
+            .build();
+
+        assert!(matches!(
+            ctx.lazy_resource::<Client>().await,
+            Err(ContextError::Factory { resource, .. }) if resource.ends_with("Client")
+        ));
+        assert_eq!(ctx.lazy_resource::<Client>().await.unwrap().0, 1);
+    }
+}
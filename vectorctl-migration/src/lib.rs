@@ -1,14 +1,25 @@
 mod cli;
 mod context;
 mod migrator;
+mod plan;
+mod presenter;
+mod report;
 mod revision;
+#[cfg(test)]
+mod test_support;
 
 use std::fmt::Debug;
 
 pub use clap::Parser;
 pub use cli::{Cli, CliError as CliMigrationError, run_migrate};
-pub use context::{Backend, Context, ContextError, Resource};
-pub use migrator::{MigrationError, MigratorTrait};
+pub use context::{Backend, Context, ContextBuilder, ContextError, Resource};
+pub use migrator::{Direction, MigrationError, MigratorTrait};
+pub use plan::{Plan, PlanStep};
+pub use presenter::Presenter;
+pub use report::{
+    HistoryRecord, HistoryReport, Outcome, RevisionRecord, RunReport, StatusReport, StepReport,
+};
+pub use vectorctl_cli::commands::{Registry, handlebars};
 
 pub fn get_file_stem(path: &str) -> &str {
     std::path::Path::new(path)
@@ -21,17 +32,34 @@ pub fn get_file_stem(path: &str) -> &str {
 pub struct Revision<'a> {
     pub message: Option<&'a str>,
     pub revision: &'a str,
-    pub down_revision: Option<&'a str>,
+    /// parent revisions, more than one for a merge revision
+    ///
+    /// Revision files written before merges were supported set an
+    /// `Option<&str>`, upgrade them by replacing `Some("parent")` with
+    /// `&["parent"]` and `None` with `&[]`.
+    pub down_revision: &'a [&'a str],
     pub date: &'a str,
 }
 
 pub trait MigrationMeta {
     fn name(&self) -> String;
     fn revision(&self) -> Revision<'_>;
+
+    /// Checksum of the migration source, recorded in the ledger to detect
+    /// edits to applied migrations. `DeriveMigrationMeta` computes it at build time.
+    fn checksum(&self) -> Option<String> {
+        None
+    }
 }
 
 #[async_trait::async_trait]
 pub trait MigrationTrait: MigrationMeta + Send + Sync + Debug {
     async fn up(&self, ctx: &context::Context) -> Result<(), MigrationError>;
     async fn down(&self, ctx: &context::Context) -> Result<(), MigrationError>;
+
+    /// Opt-in to run concurrently with the neighbouring migrations that are
+    /// independent as well. Every other migration runs alone, in revision order.
+    fn independent(&self) -> bool {
+        false
+    }
 }
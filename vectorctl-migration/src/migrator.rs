@@ -1,15 +1,22 @@
 use crate::{
     ContextError, MigrationTrait,
+    plan::Plan,
+    report::{HistoryReport, RevisionRecord, RunReport, StatusReport, StepReport},
     revision::{Node, RevisionGraph, RevisionGraphError},
 };
-use once_cell::sync::OnceCell;
-use owo_colors::OwoColorize;
-use std::{collections::HashMap, io::IsTerminal};
+use futures::future::Either;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    pin::pin,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 use uuid::Uuid;
-use vectorctl_backend::generic::{LedgerTrait, VectorTrait};
-
-static GRAPH: OnceCell<RevisionGraph> = OnceCell::new();
+use vectorctl_backend::generic::{
+    LedgerDetails, LedgerEntry, LedgerEvent, LedgerRecord, LedgerTrait,
+};
+use vectorctl_cli::commands::Registry;
 
 #[derive(Debug, Error)]
 pub enum MigrationError {
@@ -19,10 +26,26 @@ pub enum MigrationError {
     Graph(#[from] RevisionGraphError),
     #[error("migration {0} missing")]
     Missing(String),
+    #[error("multiple heads ({}), run `merge` to join them", .0.join(", "))]
+    MultipleHeads(Vec<String>),
+    #[error("applied migrations were modified ({}), pass --allow-drift to run anyway", .0.join(", "))]
+    Drift(Vec<String>),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// a migration failed after others had run, see `report` for what was executed
+    #[error("{source}")]
+    Run {
+        report: RunReport,
+        source: Box<MigrationError>,
+    },
     #[error(transparent)]
     Context(#[from] ContextError),
     #[error(transparent)]
     VectorBackend(#[from] vectorctl_backend::generic::VectorBackendError),
+    /// the lease could not be renewed, the run stopped rather than race the
+    /// runner that may now hold the lock
+    #[error("lost the migration lock: {0}")]
+    LockLost(#[source] vectorctl_backend::generic::VectorBackendError),
     #[cfg(feature = "sea-backend")]
     #[error(transparent)]
     Db(#[from] sea_orm::DbErr),
@@ -30,10 +53,13 @@ pub enum MigrationError {
     Other(String),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MigrationStatus {
     Pending,
     Applied,
+    /// applied, but the source no longer matches the recorded checksum
+    Modified,
 }
 impl std::fmt::Display for MigrationStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -43,18 +69,45 @@ impl std::fmt::Display for MigrationStatus {
             match self {
                 MigrationStatus::Pending => "Pending",
                 MigrationStatus::Applied => "Applied",
+                MigrationStatus::Modified => "Modified",
             }
         )
     }
 }
 
+impl MigrationStatus {
+    fn new(migration: &dyn MigrationTrait, entry: Option<&LedgerEntry<Uuid>>) -> Self {
+        match entry {
+            None => MigrationStatus::Pending,
+            Some(LedgerEntry {
+                checksum: Some(recorded),
+                ..
+            }) if migration
+                .checksum()
+                .is_some_and(|checksum| &checksum != recorded) =>
+            {
+                MigrationStatus::Modified
+            }
+            Some(_) => MigrationStatus::Applied,
+        }
+    }
+
+    pub fn is_applied(&self) -> bool {
+        matches!(self, MigrationStatus::Applied | MigrationStatus::Modified)
+    }
+}
+
 #[derive(Debug)]
 pub struct Migration {
     pub runner: Box<dyn MigrationTrait>,
     pub id: Option<Uuid>,
     pub status: MigrationStatus,
+    /// the ledger record, `None` while pending
+    pub entry: Option<LedgerEntry<Uuid>>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Direction {
     Up,
     Down,
@@ -65,86 +118,102 @@ pub enum Direction {
 pub trait MigratorTrait: Send {
     fn migrations() -> Vec<Box<dyn MigrationTrait>>;
 
+    /// Version of the crate the migrator is built from, recorded in the ledger.
+    /// Implement it as `Some(env!("CARGO_PKG_VERSION"))`.
+    fn version() -> Option<&'static str> {
+        None
+    }
+
+    /// Registers extra handlebars helpers for the templates `generate` and
+    /// `merge` render revisions from.
+    fn register_helpers(_registry: &mut Registry) {}
+
+    /// Builds the revision graph with the statuses of `applied`, read from the
+    /// ledger. It is built anew on every call so that it never lags behind the
+    /// ledger.
     fn build_graph(
-        applied: &HashMap<String, Uuid>,
-    ) -> Result<&'static RevisionGraph, MigrationError> {
-        Ok(GRAPH.get_or_try_init(|| {
-            RevisionGraph::try_from(
-                Self::migrations()
-                    .into_iter()
-                    .map(|migration| {
-                        let name = migration.name();
-                        let id = applied.get(&name).cloned();
-                        let status = if id.is_some() {
-                            MigrationStatus::Applied
-                        } else {
-                            MigrationStatus::Pending
-                        };
-                        Migration {
-                            runner: migration,
-                            id,
-                            status,
-                        }
-                    })
-                    .collect(),
-            )
-        })?)
+        applied: &HashMap<String, LedgerEntry<Uuid>>,
+    ) -> Result<RevisionGraph, MigrationError> {
+        Ok(RevisionGraph::try_from(
+            Self::migrations()
+                .into_iter()
+                .map(|migration| {
+                    let entry = applied.get(&migration.name());
+                    Migration {
+                        id: entry.map(|entry| entry.id),
+                        status: MigrationStatus::new(migration.as_ref(), entry),
+                        entry: entry.cloned(),
+                        runner: migration,
+                    }
+                })
+                .collect(),
+        )?)
     }
 
-    async fn status(ctx: &crate::context::Context) -> Result<(), MigrationError> {
-        let ledger = ctx.backend.ledger();
+    async fn status(ctx: &crate::context::Context) -> Result<StatusReport, MigrationError> {
+        let ledger = ctx.ledger();
         ledger.ensure().await?;
 
         let graph = Self::build_graph(&ledger.retrieve().await?)?;
 
-        let use_colors = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
-
-        graph
-            .forward_path(Some(graph.head()), graph.queue())
-            .into_iter()
-            .for_each(|Node { migration, .. }| {
-                let status_str = match migration.status {
-                    MigrationStatus::Applied => {
-                        let text = "Applied";
-                        if use_colors {
-                            text.green().bold().to_string()
-                        } else {
-                            text.to_string()
-                        }
-                    }
-                    MigrationStatus::Pending => {
-                        let text = "Pending";
-                        if use_colors {
-                            text.yellow().bold().to_string()
-                        } else {
-                            text.to_string()
-                        }
+        Ok(StatusReport {
+            revisions: graph
+                .forward_path(None)?
+                .into_iter()
+                .map(|Node { migration, .. }| {
+                    let revision = migration.runner.revision();
+                    RevisionRecord {
+                        name: migration.runner.name(),
+                        revision: revision.revision.to_owned(),
+                        parent: revision
+                            .down_revision
+                            .iter()
+                            .map(|parent| parent.to_string())
+                            .collect(),
+                        status: migration.status.clone(),
+                        applied_at: migration.entry.as_ref().map(|entry| entry.applied_at),
+                        message: revision.message.map(str::to_owned),
+                        checksum: migration
+                            .entry
+                            .as_ref()
+                            .and_then(|entry| entry.checksum.clone()),
+                        format_version: migration.entry.as_ref().map(|entry| entry.format_version),
+                        details: migration.entry.as_ref().map(|entry| entry.details.clone()),
                     }
-                };
-
-                let message = migration
-                    .runner
-                    .revision()
-                    .message
-                    .map(|message| {
-                        if use_colors {
-                            format!(" — {}", message.dimmed().italic())
-                        } else {
-                            format!(" — {}", message)
-                        }
-                    })
-                    .unwrap_or_default();
+                })
+                .collect(),
+        })
+    }
 
-                let name = if use_colors {
-                    migration.runner.name().blue().bold().to_string()
-                } else {
-                    migration.runner.name().to_string()
-                };
+    /// Every up and down event recorded in the ledger, oldest first.
+    async fn history(ctx: &crate::context::Context) -> Result<HistoryReport, MigrationError> {
+        let ledger = ctx.ledger();
+        ledger.ensure().await?;
 
-                println!("{:<20} | {}{}", name, status_str, message);
-            });
+        Ok(HistoryReport {
+            events: ledger
+                .history()
+                .await?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        })
+    }
 
-        Ok(())
+    /// Latest revision of every unmerged branch, read from the migrations alone.
+    fn heads() -> Result<Vec<String>, MigrationError> {
+        let graph = RevisionGraph::try_from(
+            Self::migrations()
+                .into_iter()
+                .map(|runner| Migration {
+                    runner,
+                    id: None,
+                    status: MigrationStatus::Pending,
+                    entry: None,
+                })
+                .collect(),
+        )?;
+        Ok(graph.heads().into_iter().map(str::to_owned).collect())
     }
 
     fn latest_revision() -> Result<Box<dyn MigrationTrait>, MigrationError> {
@@ -154,148 +223,658 @@ pub trait MigratorTrait: Send {
             .ok_or_else(|| MigrationError::Missing("no migrations".into()))
     }
 
-    async fn refresh(ctx: &crate::context::Context) -> Result<(), MigrationError> {
-        Self::exec(ctx, None, None, Direction::Refresh).await
+    async fn refresh(ctx: &crate::context::Context) -> Result<RunReport, MigrationError> {
+        Self::exec(ctx, None, Direction::Refresh, false).await
     }
 
-    async fn reset(ctx: &crate::context::Context) -> Result<(), MigrationError> {
-        Self::exec(ctx, None, None, Direction::Down).await
+    async fn reset(ctx: &crate::context::Context) -> Result<RunReport, MigrationError> {
+        Self::exec(ctx, None, Direction::Down, false).await
     }
 
-    async fn up(ctx: &crate::context::Context, to: Option<String>) -> Result<(), MigrationError> {
-        Self::exec(ctx, None, to.as_deref(), Direction::Up).await
+    async fn up(
+        ctx: &crate::context::Context,
+        to: Option<String>,
+        allow_drift: bool,
+    ) -> Result<RunReport, MigrationError> {
+        Self::exec(ctx, to.as_deref(), Direction::Up, allow_drift).await
     }
 
-    async fn down(ctx: &crate::context::Context, to: Option<String>) -> Result<(), MigrationError> {
-        Self::exec(ctx, None, to.as_deref(), Direction::Down).await
+    async fn down(
+        ctx: &crate::context::Context,
+        to: Option<String>,
+    ) -> Result<RunReport, MigrationError> {
+        Self::exec(ctx, to.as_deref(), Direction::Down, false).await
     }
 
+    /// Runs the plan for `direction`. When a migration fails after others ran,
+    /// the error is a [`MigrationError::Run`] carrying what was executed.
     async fn exec(
         ctx: &crate::context::Context,
-        from: Option<&str>,
         to: Option<&str>,
         direction: Direction,
-    ) -> Result<(), MigrationError> {
-        let ledger = ctx.backend.ledger();
+        allow_drift: bool,
+    ) -> Result<RunReport, MigrationError> {
+        let ledger = ctx.ledger();
         ledger.ensure().await?;
 
-        let applied = ledger.retrieve().await?;
-        let graph = Self::build_graph(&applied)?;
-        let path = match direction {
-            Direction::Up => graph.forward_path(
-                Some(from.unwrap_or(graph.head())),
-                to.unwrap_or(graph.queue()),
-            ),
-            Direction::Down => graph.backward_path(Some(graph.queue()), to),
-            Direction::Refresh => graph.backward_path(Some(graph.queue()), None),
-        };
+        let owner = lock_owner();
+        let lease = lock_lease(ctx);
+        ledger.acquire_lock(&owner, lease).await?;
 
-        let iterator = path
-            .into_iter()
-            .filter(|Node { migration, .. }| match direction {
-                Direction::Up => migration.status == MigrationStatus::Pending,
-                Direction::Down | Direction::Refresh => {
-                    migration.status == MigrationStatus::Applied
+        let audit = audit(Self::version());
+        let mut report = RunReport::default();
+        let result = hold_lock(&ledger, &owner, lease, async {
+            let graph = Self::build_graph(&ledger.retrieve().await?)?;
+            if direction == Direction::Up && !allow_drift {
+                check_drift(&graph)?;
+            }
+            let plan = Plan::new(&graph, to, direction)?;
+
+            for steps in plan.steps.chunk_by(|a, b| a.direction == b.direction) {
+                let migrations = steps
+                    .iter()
+                    .map(|step| {
+                        graph
+                            .get(&step.revision)
+                            .ok_or_else(|| RevisionGraphError::NotFound(step.revision.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                match steps[0].direction {
+                    Direction::Down => {
+                        run_down(ctx, &audit, migrations.into_iter(), &mut report).await?
+                    }
+                    _ => run_up(ctx, &audit, migrations.into_iter(), &mut report).await?,
                 }
-            })
-            .map(|Node { migration, .. }| (migration.id, migration.runner.as_ref()));
+            }
 
-        match direction {
-            Direction::Up => {
-                run_up(ctx, iterator).await?;
+            Ok(())
+        })
+        .await;
+
+        let released = ledger.release_lock(&owner).await;
+        match result {
+            Err(source) if !report.steps.is_empty() => Err(MigrationError::Run {
+                report,
+                source: Box::new(source),
+            }),
+            result => {
+                result?;
+                released?;
+                Ok(report)
             }
-            Direction::Down => {
-                run_down(ctx, iterator).await?;
+        }
+    }
+
+    async fn dry_run(
+        ctx: &crate::context::Context,
+        to: Option<String>,
+        direction: Direction,
+        allow_drift: bool,
+    ) -> Result<Plan, MigrationError> {
+        let ledger = ctx.ledger();
+        ledger.ensure().await?;
+
+        let graph = Self::build_graph(&ledger.retrieve().await?)?;
+        if direction == Direction::Up && !allow_drift {
+            check_drift(&graph)?;
+        }
+        Ok(Plan::new(&graph, to.as_deref(), direction)?)
+    }
+
+    /// Records in the ledger that exactly `revision` and its ancestors are
+    /// applied, without running any migration. With `purge`, every record of
+    /// the ledger is erased first, including those of unknown migrations.
+    /// Returns the ledger changes made.
+    async fn stamp(
+        ctx: &crate::context::Context,
+        revision: &str,
+        purge: bool,
+    ) -> Result<Plan, MigrationError> {
+        let ledger = ctx.ledger();
+        ledger.ensure().await?;
+
+        let owner = lock_owner();
+        let lease = lock_lease(ctx);
+        ledger.acquire_lock(&owner, lease).await?;
+
+        let audit = LedgerDetails {
+            stamped: true,
+            ..audit(Self::version())
+        };
+        let result = hold_lock(&ledger, &owner, lease, async {
+            if purge {
+                let ids = ledger
+                    .history()
+                    .await?
+                    .into_iter()
+                    .map(|entry| entry.id)
+                    .collect();
+                ledger.delete_many(ids).await?;
             }
-            Direction::Refresh => {
-                let collected: Vec<_> = iterator.collect();
-                run_down(ctx, collected.iter().cloned()).await?;
-                run_up(ctx, collected.into_iter()).await?;
+
+            let graph = Self::build_graph(&ledger.retrieve().await?)?;
+            let plan = Plan::stamp(&graph, revision)?;
+            let records = plan
+                .steps
+                .iter()
+                .map(|step| {
+                    let (_, migration) = graph
+                        .get(&step.revision)
+                        .ok_or_else(|| RevisionGraphError::NotFound(step.revision.clone()))?;
+                    let event = match step.direction {
+                        Direction::Down => LedgerEvent::Down,
+                        _ => LedgerEvent::Up,
+                    };
+                    Ok(ledger_record(migration, event, audit.clone()))
+                })
+                .collect::<Result<Vec<_>, MigrationError>>()?;
+            if !records.is_empty() {
+                ledger.insert_many(records).await?;
             }
-        };
 
+            Ok(plan)
+        })
+        .await;
+
+        let released = ledger.release_lock(&owner).await;
+        let plan = result?;
+        released?;
+        Ok(plan)
+    }
+
+    async fn unlock(ctx: &crate::context::Context) -> Result<(), MigrationError> {
+        let ledger = ctx.ledger();
+        ledger.ensure().await?;
+        ledger.force_unlock().await?;
         Ok(())
     }
 }
 
-async fn run_down<'a, I>(ctx: &crate::context::Context, iterator: I) -> Result<(), MigrationError>
-where
-    I: Iterator<Item = (Option<Uuid>, &'a dyn MigrationTrait)> + Send,
-{
-    let ledger = ctx.backend.ledger();
-    ledger.ensure().await?;
+/// How long a run holds the migration lock between renewals unless
+/// [`LedgerOptions::lock_lease`](vectorctl_backend::generic::LedgerOptions::lock_lease)
+/// says otherwise.
+const DEFAULT_LOCK_LEASE: Duration = Duration::from_secs(10 * 60);
 
-    let use_colors = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+fn lock_lease(ctx: &crate::context::Context) -> Duration {
+    ctx.ledger.lock_lease.unwrap_or(DEFAULT_LOCK_LEASE)
+}
 
-    let ids = futures::future::try_join_all(iterator.map(|(id_opt, migration)| {
-        let name = migration.name();
-        async move {
-            let message = format!("Running down: {}", name);
-            if use_colors {
-                println!("{}", message.yellow().bold());
-            } else {
-                println!("{message}");
+/// Runs `work` while renewing the lease of `owner` every third of it, so that
+/// a migration outlasting the lease keeps the lock. When a renewal fails,
+/// `work` is dropped where it stands and the run fails with
+/// [`MigrationError::LockLost`].
+async fn hold_lock<T>(
+    ledger: &impl LedgerTrait,
+    owner: &str,
+    lease: Duration,
+    work: impl Future<Output = Result<T, MigrationError>>,
+) -> Result<T, MigrationError> {
+    let heartbeat = async {
+        loop {
+            tokio::time::sleep(lease / 3).await;
+            if let Err(err) = ledger.acquire_lock(owner, lease).await {
+                return err;
             }
+        }
+    };
 
-            let id = id_opt.ok_or_else(|| {
-                MigrationError::Graph(RevisionGraphError::NotFound(format!("{:?}", name)))
-            })?;
+    match futures::future::select(pin!(work), pin!(heartbeat)).await {
+        Either::Left((result, _)) => result,
+        Either::Right((err, _)) => Err(MigrationError::LockLost(err)),
+    }
+}
 
-            migration.down(ctx).await.map(|_| {
-                let message = format!("Rolled back: {}", name);
-                if use_colors {
-                    println!("{}", message.green().bold());
-                } else {
-                    println!("{message}");
-                }
-                id
-            })
+/// Refuses to go on while an applied migration no longer matches its source.
+fn check_drift(graph: &RevisionGraph) -> Result<(), MigrationError> {
+    let modified = graph
+        .forward_path(None)?
+        .into_iter()
+        .filter(|Node { migration, .. }| migration.status == MigrationStatus::Modified)
+        .map(|Node { migration, .. }| migration.runner.name())
+        .collect::<Vec<_>>();
+
+    if modified.is_empty() {
+        Ok(())
+    } else {
+        Err(MigrationError::Drift(modified))
+    }
+}
+
+fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_owned())
+        .filter(|host| !host.is_empty())
+}
+
+fn username() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+/// Who runs the migrations and with which versions, recorded with every event.
+fn audit(migrator_version: Option<&str>) -> LedgerDetails {
+    LedgerDetails {
+        host: hostname(),
+        user: username(),
+        vectorctl_version: Some(env!("CARGO_PKG_VERSION").into()),
+        migrator_version: migrator_version.map(str::to_owned),
+        ..Default::default()
+    }
+}
+
+fn lock_owner() -> String {
+    let host = hostname().unwrap_or_else(|| "unknown".into());
+    format!("{host}/{}/{}", std::process::id(), Uuid::now_v7())
+}
+
+type Step<'a> = (Option<Uuid>, &'a dyn MigrationTrait);
+
+/// Splits a path into batches that are awaited one after the other. A run of
+/// consecutive independent migrations shares a batch, any other migration is
+/// alone in its own.
+fn batches<'a, I>(iterator: I) -> Vec<Vec<Step<'a>>>
+where
+    I: Iterator<Item = Step<'a>>,
+{
+    iterator.fold(Vec::new(), |mut batches: Vec<Vec<Step<'a>>>, step| {
+        match batches.last_mut() {
+            Some(batch)
+                if step.1.independent()
+                    && batch.iter().all(|(_, migration)| migration.independent()) =>
+            {
+                batch.push(step)
+            }
+            _ => batches.push(vec![step]),
         }
-    }))
-    .await?;
+        batches
+    })
+}
+
+/// Awaits every migration of the batch to completion, so that each one which
+/// succeeded is recorded even when a sibling fails, then reports the first error.
+async fn run_batch<F>(batch: Vec<F>, report: &mut RunReport) -> Result<(), MigrationError>
+where
+    F: Future<Output = (StepReport, Result<(), MigrationError>)>,
+{
+    let (steps, results): (Vec<_>, Vec<_>) =
+        futures::future::join_all(batch).await.into_iter().unzip();
+    report.steps.extend(steps);
+    results.into_iter().collect::<Result<Vec<_>, _>>()?;
+    Ok(())
+}
 
-    if !ids.is_empty() {
-        ledger.delete_many(ids).await?;
+/// The ledger event recording `migration` going in the direction of `event`,
+/// with its revision added to `details`.
+fn ledger_record(
+    migration: &dyn MigrationTrait,
+    event: LedgerEvent,
+    details: LedgerDetails,
+) -> LedgerRecord {
+    let revision = migration.revision();
+    LedgerRecord {
+        name: migration.name(),
+        event,
+        checksum: migration.checksum(),
+        details: LedgerDetails {
+            revision: Some(revision.revision.to_owned()),
+            down_revision: revision
+                .down_revision
+                .iter()
+                .map(|parent| parent.to_string())
+                .collect(),
+            ..details
+        },
+    }
+}
+
+async fn run_down<'a, I>(
+    ctx: &crate::context::Context,
+    audit: &LedgerDetails,
+    iterator: I,
+    report: &mut RunReport,
+) -> Result<(), MigrationError>
+where
+    I: Iterator<Item = Step<'a>> + Send,
+{
+    let ledger = ctx.ledger();
+    ledger.ensure().await?;
+
+    for batch in batches(iterator) {
+        run_batch(
+            batch
+                .into_iter()
+                .map(|(_, migration)| {
+                    let ledger = &ledger;
+                    async move {
+                        let started = Instant::now();
+                        let result = async {
+                            migration.down(ctx).await?;
+                            ledger
+                                .insert_many(vec![ledger_record(
+                                    migration,
+                                    LedgerEvent::Down,
+                                    LedgerDetails {
+                                        duration: Some(started.elapsed()),
+                                        ..audit.clone()
+                                    },
+                                )])
+                                .await?;
+                            Ok(())
+                        }
+                        .await;
+
+                        (
+                            StepReport::new(migration, Direction::Down, started.elapsed(), &result),
+                            result,
+                        )
+                    }
+                })
+                .collect(),
+            report,
+        )
+        .await?;
     }
 
     Ok(())
 }
 
-async fn run_up<'a, I>(ctx: &crate::context::Context, iterator: I) -> Result<(), MigrationError>
+async fn run_up<'a, I>(
+    ctx: &crate::context::Context,
+    audit: &LedgerDetails,
+    iterator: I,
+    report: &mut RunReport,
+) -> Result<(), MigrationError>
 where
-    I: Iterator<Item = (Option<Uuid>, &'a dyn MigrationTrait)> + Send,
+    I: Iterator<Item = Step<'a>> + Send,
 {
-    let ledger = ctx.backend.ledger();
+    let ledger = ctx.ledger();
     ledger.ensure().await?;
 
-    let use_colors = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    for batch in batches(iterator) {
+        run_batch(
+            batch
+                .into_iter()
+                .map(|(_, migration)| {
+                    let ledger = &ledger;
+                    async move {
+                        let started = Instant::now();
+                        let result = async {
+                            migration.up(ctx).await?;
+                            ledger
+                                .insert_many(vec![ledger_record(
+                                    migration,
+                                    LedgerEvent::Up,
+                                    LedgerDetails {
+                                        duration: Some(started.elapsed()),
+                                        ..audit.clone()
+                                    },
+                                )])
+                                .await?;
+                            Ok(())
+                        }
+                        .await;
 
-    let ids = futures::future::try_join_all(iterator.map(|(_, migration)| {
-        let name = migration.name();
-        async move {
-            let message = format!("Applying: {}", name);
-            if use_colors {
-                println!("{}", message.yellow().bold());
-            } else {
-                println!("{message}");
-            }
+                        (
+                            StepReport::new(migration, Direction::Up, started.elapsed(), &result),
+                            result,
+                        )
+                    }
+                })
+                .collect(),
+            report,
+        )
+        .await?;
+    }
 
-            migration.up(ctx).await.map(|_| {
-                let message = format!("Applied: {}", name);
-                if use_colors {
-                    println!("{}", message.green().bold());
-                } else {
-                    println!("{message}");
-                }
-                name
-            })
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{report::Outcome, test_support::TestMigration};
+    use chrono::Utc;
+    use vectorctl_backend::generic::{LedgerOptions, VectorBackendError};
+
+    #[test]
+    fn test_batches_keep_order_and_only_group_independent_runs() {
+        let migrations = [
+            TestMigration::new("a", &[]),
+            TestMigration::new("b", &[]).independent(),
+            TestMigration::new("c", &[]).independent(),
+            TestMigration::new("d", &[]),
+            TestMigration::new("e", &[]).independent(),
+        ];
+
+        let batches = batches(
+            migrations
+                .iter()
+                .map(|migration| (None, migration as &dyn MigrationTrait)),
+        );
+
+        assert_eq!(
+            batches
+                .iter()
+                .map(|batch| batch.iter().map(|(_, m)| m.name()).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            vec![
+                vec!["version_a"],
+                vec!["version_b", "version_c"],
+                vec!["version_d"],
+                vec!["version_e"]
+            ]
+        );
+    }
+
+    struct Chain;
+
+    impl MigratorTrait for Chain {
+        fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+            vec![
+                Box::new(TestMigration::new("a", &[])),
+                Box::new(TestMigration::new("b", &["a"])),
+            ]
         }
-    }))
-    .await?;
+    }
+
+    async fn statuses(ctx: &crate::context::Context) -> Vec<MigrationStatus> {
+        Chain::status(ctx)
+            .await
+            .unwrap()
+            .revisions
+            .into_iter()
+            .map(|revision| revision.status)
+            .collect()
+    }
 
-    if !ids.is_empty() {
-        ledger.insert_many(ids).await?;
+    #[tokio::test]
+    async fn test_graph_follows_the_ledger_across_runs() {
+        let ctx = crate::context::Context::from_url("memory://", None).unwrap();
+        assert_eq!(
+            statuses(&ctx).await,
+            vec![MigrationStatus::Pending, MigrationStatus::Pending]
+        );
+
+        Chain::up(&ctx, None, false).await.unwrap();
+        assert_eq!(
+            statuses(&ctx).await,
+            vec![MigrationStatus::Applied, MigrationStatus::Applied]
+        );
+
+        let report = Chain::down(&ctx, Some("a".into())).await.unwrap();
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(
+            statuses(&ctx).await,
+            vec![MigrationStatus::Applied, MigrationStatus::Pending]
+        );
+
+        // a second context starts from its own, empty ledger
+        let other = crate::context::Context::from_url("memory://", None).unwrap();
+        assert_eq!(
+            statuses(&other).await,
+            vec![MigrationStatus::Pending, MigrationStatus::Pending]
+        );
+        assert_eq!(Chain::history(&ctx).await.unwrap().events.len(), 3);
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn test_stamp_records_without_running_and_purge_clears_the_history() {
+        let ctx = crate::context::Context::from_url("memory://", None).unwrap();
+        Chain::up(&ctx, None, false).await.unwrap();
+
+        let plan = Chain::stamp(&ctx, "a", true).await.unwrap();
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(
+            statuses(&ctx).await,
+            vec![MigrationStatus::Applied, MigrationStatus::Pending]
+        );
+        let events = Chain::history(&ctx).await.unwrap().events;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "version_a");
+        assert!(events[0].details.stamped);
+
+        Chain::stamp(&ctx, "b", false).await.unwrap();
+        assert_eq!(
+            statuses(&ctx).await,
+            vec![MigrationStatus::Applied, MigrationStatus::Applied]
+        );
+        assert_eq!(Chain::history(&ctx).await.unwrap().events.len(), 2);
+    }
+
+    struct BrokenChain;
+
+    impl MigratorTrait for BrokenChain {
+        fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+            vec![
+                Box::new(TestMigration::new("a", &[])),
+                Box::new(TestMigration::new("b", &["a"])),
+                Box::new(TestMigration::new("c", &["b"]).failing()),
+            ]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_keeps_the_migrations_before_it() {
+        let ctx = crate::context::Context::from_url("memory://", None).unwrap();
+        let Err(MigrationError::Run { report, source }) = BrokenChain::up(&ctx, None, false).await
+        else {
+            panic!("the run should fail with its report");
+        };
+        assert_eq!(source.to_string(), "Other c failed");
+        assert_eq!(
+            report
+                .steps
+                .iter()
+                .map(|step| (step.name.as_str(), step.outcome))
+                .collect::<Vec<_>>(),
+            vec![
+                ("version_a", Outcome::Applied),
+                ("version_b", Outcome::Applied),
+                ("version_c", Outcome::Failed),
+            ]
+        );
+
+        let applied = ctx.ledger().retrieve().await.unwrap();
+        let mut names = applied.keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["version_a", "version_b"]);
+        // the lock was released, the next run resumes at the failed migration
+        let Err(MigrationError::Run { report, .. }) = BrokenChain::up(&ctx, None, false).await
+        else {
+            panic!("the run should fail with its report");
+        };
+        assert_eq!(report.steps.len(), 1);
+    }
+
+    struct Slow;
+
+    impl MigratorTrait for Slow {
+        fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+            vec![Box::new(
+                TestMigration::new("a", &[]).taking(Duration::from_millis(200)),
+            )]
+        }
+    }
+
+    fn short_lease_context() -> crate::context::Context {
+        crate::context::Context::from_url("memory://", None)
+            .unwrap()
+            .with_ledger(LedgerOptions {
+                lock_lease: Some(Duration::from_millis(60)),
+                ..Default::default()
+            })
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_renewed_while_a_migration_outlasts_its_lease() {
+        let ctx = short_lease_context();
+        let intruder = async {
+            tokio::time::sleep(Duration::from_millis(120)).await;
+            ctx.ledger()
+                .acquire_lock("intruder", Duration::from_secs(60))
+                .await
+        };
+
+        let (run, intruder) = futures::join!(Slow::up(&ctx, None, false), intruder);
+
+        run.unwrap();
+        assert!(matches!(intruder, Err(VectorBackendError::Locked { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_once_its_lock_is_lost() {
+        let ctx = short_lease_context();
+        let intruder = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let ledger = ctx.ledger();
+            ledger.force_unlock().await.unwrap();
+            ledger
+                .acquire_lock("intruder", Duration::from_secs(60))
+                .await
+                .unwrap();
+        };
+
+        let (run, ()) = futures::join!(Slow::up(&ctx, None, false), intruder);
+
+        assert!(matches!(
+            run,
+            Err(MigrationError::LockLost(VectorBackendError::Locked { owner, .. }))
+                if owner == "intruder"
+        ));
+        assert!(ctx.ledger().retrieve().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_status_compares_recorded_checksum() {
+        let migration = TestMigration::new("a", &[]);
+        let entry = |checksum: Option<&str>| LedgerEntry {
+            id: Uuid::now_v7(),
+            checksum: checksum.map(str::to_string),
+            applied_at: Utc::now(),
+            format_version: 1,
+            details: LedgerDetails::default(),
+        };
+
+        assert_eq!(
+            MigrationStatus::new(&migration, None),
+            MigrationStatus::Pending
+        );
+        assert_eq!(
+            MigrationStatus::new(&migration, Some(&entry(Some("a-checksum")))),
+            MigrationStatus::Applied
+        );
+        assert_eq!(
+            MigrationStatus::new(&migration, Some(&entry(None))),
+            MigrationStatus::Applied
+        );
+        assert_eq!(
+            MigrationStatus::new(&migration, Some(&entry(Some("edited")))),
+            MigrationStatus::Modified
+        );
+        assert!(MigrationStatus::Modified.is_applied());
+    }
 }
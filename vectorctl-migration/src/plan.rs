@@ -0,0 +1,306 @@
+use crate::{
+    migrator::{Direction, MigrationStatus},
+    revision::{Node, RevisionGraph, RevisionGraphError, Target},
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlanStep {
+    /// either [`Direction::Up`] or [`Direction::Down`]
+    pub direction: Direction,
+    /// migration name
+    pub name: String,
+    /// revision ID
+    pub revision: String,
+    /// ledger entry rolled back by a [`Direction::Down`] step
+    pub id: Option<Uuid>,
+}
+
+impl PlanStep {
+    fn new(Node { migration, .. }: &Node, direction: Direction) -> Self {
+        Self {
+            direction,
+            name: migration.runner.name(),
+            revision: migration.runner.revision().revision.to_owned(),
+            id: migration.id,
+        }
+    }
+}
+
+/// The ordered migrations a run executes, computed from the revision graph and
+/// the ledger without touching either.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    /// `to` is any target accepted by [`RevisionGraph::resolve`]. Going up, the
+    /// pending revisions up to and including `to` are applied. Going down, the
+    /// applied revisions that `to` does not descend from are rolled back, which
+    /// leaves `to` as the current revision; `to` must therefore be applied.
+    pub fn new(
+        graph: &RevisionGraph,
+        to: Option<&str>,
+        direction: Direction,
+    ) -> Result<Self, RevisionGraphError> {
+        let target = to.map(|to| graph.resolve(to)).transpose()?;
+        if let (Direction::Down, Some(Target::Revision(revision))) = (direction, target)
+            && !graph
+                .node(revision)
+                .is_some_and(|node| node.migration.status.is_applied())
+        {
+            return Err(RevisionGraphError::NotApplied(revision.to_owned()));
+        }
+
+        Ok(Self {
+            steps: Self::steps(graph, target, direction)?,
+        })
+    }
+
+    fn steps(
+        graph: &RevisionGraph,
+        target: Option<Target>,
+        direction: Direction,
+    ) -> Result<Vec<PlanStep>, RevisionGraphError> {
+        let revision = match target {
+            Some(Target::Revision(revision)) => Some(revision),
+            _ => None,
+        };
+        let path = match direction {
+            Direction::Up if target == Some(Target::Base) => Vec::new(),
+            Direction::Up => graph.forward_path(revision)?,
+            Direction::Down => graph.backward_path(revision)?,
+            Direction::Refresh => graph.backward_path(None)?,
+        };
+
+        let nodes = path
+            .into_iter()
+            .filter(|Node { migration, .. }| match direction {
+                Direction::Up => migration.status == MigrationStatus::Pending,
+                Direction::Down | Direction::Refresh => migration.status.is_applied(),
+            })
+            .collect::<Vec<_>>();
+
+        let steps = match direction {
+            Direction::Up => nodes
+                .into_iter()
+                .map(|node| PlanStep::new(node, Direction::Up))
+                .collect(),
+            Direction::Down => nodes
+                .into_iter()
+                .map(|node| PlanStep::new(node, Direction::Down))
+                .collect(),
+            Direction::Refresh => nodes
+                .iter()
+                .map(|node| PlanStep::new(node, Direction::Down))
+                .chain(
+                    nodes
+                        .iter()
+                        .rev()
+                        .map(|node| PlanStep::new(node, Direction::Up)),
+                )
+                .collect(),
+        };
+
+        Ok(steps)
+    }
+
+    /// Ledger changes that leave exactly the `revision` target and its ancestors
+    /// applied: every other applied migration is rolled back, children first,
+    /// then every pending ancestor is applied, parents first.
+    pub fn stamp(graph: &RevisionGraph, revision: &str) -> Result<Self, RevisionGraphError> {
+        let target = Some(graph.resolve(revision)?);
+        let mut steps = Self::steps(graph, target, Direction::Down)?;
+        steps.extend(Self::steps(graph, target, Direction::Up)?);
+        Ok(Self { steps })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{migrator::Migration, test_support::TestMigration};
+
+    fn graph(applied: &[&str]) -> RevisionGraph {
+        RevisionGraph::try_from(
+            [("a", &[][..]), ("b", &["a"]), ("c", &["b"])]
+                .into_iter()
+                .map(|(rev, down_rev)| {
+                    let is_applied = applied.contains(&rev);
+                    Migration {
+                        runner: Box::new(TestMigration::new(rev, down_rev)),
+                        id: is_applied.then(Uuid::now_v7),
+                        entry: None,
+                        status: if is_applied {
+                            MigrationStatus::Applied
+                        } else {
+                            MigrationStatus::Pending
+                        },
+                    }
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn steps(plan: &Plan) -> Vec<(Direction, &str)> {
+        plan.steps
+            .iter()
+            .map(|step| (step.direction, step.revision.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_up_plan_skips_applied() {
+        let plan = Plan::new(&graph(&["a"]), None, Direction::Up).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Up, "b"), (Direction::Up, "c")]
+        );
+        assert_eq!(plan.steps[0].name, "version_b");
+    }
+
+    #[test]
+    fn test_down_plan_only_rolls_back_applied() {
+        let plan = Plan::new(&graph(&["a", "b"]), None, Direction::Down).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Down, "b"), (Direction::Down, "a")]
+        );
+        assert!(plan.steps.iter().all(|step| step.id.is_some()));
+    }
+
+    #[test]
+    fn test_refresh_plan_reapplies_in_revision_order() {
+        let plan = Plan::new(&graph(&["a", "b", "c"]), None, Direction::Refresh).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![
+                (Direction::Down, "c"),
+                (Direction::Down, "b"),
+                (Direction::Down, "a"),
+                (Direction::Up, "a"),
+                (Direction::Up, "b"),
+                (Direction::Up, "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plans_resolve_symbolic_targets() {
+        let plan = Plan::new(&graph(&["a", "b"]), Some("-1"), Direction::Down).unwrap();
+        assert_eq!(steps(&plan), vec![(Direction::Down, "b")]);
+
+        let plan = Plan::new(&graph(&["a", "b"]), Some("base"), Direction::Down).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Down, "b"), (Direction::Down, "a")]
+        );
+
+        let plan = Plan::new(&graph(&["a"]), Some("+1"), Direction::Up).unwrap();
+        assert_eq!(steps(&plan), vec![(Direction::Up, "b")]);
+
+        let plan = Plan::new(&graph(&[]), Some("version_b"), Direction::Up).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Up, "a"), (Direction::Up, "b")]
+        );
+
+        assert!(
+            Plan::new(&graph(&[]), Some("base"), Direction::Up)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_stamp_plan_sets_ancestors_applied() {
+        let plan = Plan::stamp(&graph(&["a"]), "b").unwrap();
+        assert_eq!(steps(&plan), vec![(Direction::Up, "b")]);
+
+        let plan = Plan::stamp(&graph(&["a", "b", "c"]), "a").unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Down, "c"), (Direction::Down, "b")]
+        );
+
+        // a partial history is repaired in both directions at once
+        let plan = Plan::stamp(&graph(&["c"]), "b").unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![
+                (Direction::Down, "c"),
+                (Direction::Up, "a"),
+                (Direction::Up, "b"),
+            ]
+        );
+
+        assert!(Plan::stamp(&graph(&["a", "b"]), "b").unwrap().is_empty());
+        assert!(matches!(
+            Plan::stamp(&graph(&[]), "z"),
+            Err(RevisionGraphError::UnknownTarget(rev)) if rev == "z"
+        ));
+    }
+
+    #[test]
+    fn test_down_to_leaves_target_current() {
+        let plan = Plan::new(&graph(&["a", "b", "c"]), Some("a"), Direction::Down).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Down, "c"), (Direction::Down, "b")]
+        );
+
+        let plan = Plan::new(&graph(&["a", "b", "c"]), Some("c"), Direction::Down).unwrap();
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_down_to_starts_from_the_ledger() {
+        // only what the ledger holds is rolled back, pending revisions past
+        // the target are left alone
+        let plan = Plan::new(&graph(&["a", "b"]), Some("a"), Direction::Down).unwrap();
+        assert_eq!(steps(&plan), vec![(Direction::Down, "b")]);
+
+        // a partial history with a hole below the newest applied revision
+        let plan = Plan::new(&graph(&["a", "c"]), Some("a"), Direction::Down).unwrap();
+        assert_eq!(steps(&plan), vec![(Direction::Down, "c")]);
+
+        let plan = Plan::new(&graph(&["a", "c"]), None, Direction::Down).unwrap();
+        assert_eq!(
+            steps(&plan),
+            vec![(Direction::Down, "c"), (Direction::Down, "a")]
+        );
+    }
+
+    #[test]
+    fn test_down_to_rejects_unknown_and_pending_targets() {
+        assert!(matches!(
+            Plan::new(&graph(&["a", "b"]), Some("typo"), Direction::Down),
+            Err(RevisionGraphError::UnknownTarget(rev)) if rev == "typo"
+        ));
+        assert!(matches!(
+            Plan::new(&graph(&["a"]), Some("c"), Direction::Down),
+            Err(RevisionGraphError::NotApplied(rev)) if rev == "c"
+        ));
+        assert!(
+            Plan::new(&graph(&[]), Some("base"), Direction::Down)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_empty_plan_when_up_to_date() {
+        assert!(
+            Plan::new(&graph(&["a", "b", "c"]), None, Direction::Up)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    migrator::{Direction, MigrationError, MigrationStatus},
+    plan::Plan,
+    report::{HistoryReport, Outcome, RevisionRecord, RunReport, StatusReport},
+};
+use owo_colors::{OwoColorize, Style};
+use serde::Serialize;
+use std::io::IsTerminal;
+use vectorctl_backend::generic::LedgerEvent;
+use vectorctl_cli::commands::OutputFormat;
+
+/// Renders the reports returned by [`crate::MigratorTrait`] on stdout, either
+/// as colored text or as JSON.
+#[derive(Debug, Clone, Copy)]
+pub struct Presenter {
+    format: OutputFormat,
+    use_colors: bool,
+}
+
+impl Presenter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            use_colors: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    /// With `verbose`, every applied migration is followed by its ledger record.
+    pub fn status(&self, report: &StatusReport, verbose: bool) -> Result<(), MigrationError> {
+        if self.format == OutputFormat::Json {
+            return print_json(report);
+        }
+
+        report.revisions.iter().for_each(|revision| {
+            let status_str = match revision.status {
+                MigrationStatus::Applied => self.paint("Applied", Style::new().green().bold()),
+                MigrationStatus::Pending => self.paint("Pending", Style::new().yellow().bold()),
+                MigrationStatus::Modified => self.paint("Modified", Style::new().red().bold()),
+            };
+
+            let message = revision
+                .message
+                .as_deref()
+                .map(|message| {
+                    format!(" — {}", self.paint(message, Style::new().dimmed().italic()))
+                })
+                .unwrap_or_default();
+
+            let name = self.paint(&revision.name, Style::new().blue().bold());
+
+            println!("{:<20} | {}{}", name, status_str, message);
+
+            if verbose {
+                self.status_details(revision);
+            }
+        });
+
+        Ok(())
+    }
+
+    pub fn run(&self, report: &RunReport) -> Result<(), MigrationError> {
+        if self.format == OutputFormat::Json {
+            return print_json(report);
+        }
+
+        if report.steps.is_empty() {
+            println!("Nothing to do");
+        }
+
+        report.steps.iter().for_each(|step| {
+            let message = match step.outcome {
+                Outcome::Applied => self.paint(
+                    &format!("Applied: {}", step.name),
+                    Style::new().green().bold(),
+                ),
+                Outcome::RolledBack => self.paint(
+                    &format!("Rolled back: {}", step.name),
+                    Style::new().green().bold(),
+                ),
+                Outcome::Failed => {
+                    self.paint(&format!("Failed: {}", step.name), Style::new().red().bold())
+                }
+            };
+            println!("{} ({} ms)", message, step.duration.as_millis());
+        });
+
+        Ok(())
+    }
+
+    pub fn plan(&self, plan: &Plan) -> Result<(), MigrationError> {
+        if self.format == OutputFormat::Json {
+            return print_json(plan);
+        }
+
+        if plan.is_empty() {
+            println!("Nothing to do");
+        }
+
+        plan.steps.iter().for_each(|step| {
+            let (direction, ledger) = match step.direction {
+                Direction::Down => (
+                    "down",
+                    format!(
+                        "record rollback of ledger entry {}",
+                        step.id.map(|id| id.to_string()).unwrap_or_default()
+                    ),
+                ),
+                _ => ("up", format!("record ledger entry {}", step.name)),
+            };
+            let direction = self.paint(&format!("{:<4}", direction), Style::new().yellow().bold());
+            let name = self.paint(&step.name, Style::new().blue().bold());
+
+            println!(
+                "{} | {:<20} | {} | {}",
+                direction, name, step.revision, ledger
+            );
+        });
+
+        Ok(())
+    }
+
+    pub fn history(&self, report: &HistoryReport) -> Result<(), MigrationError> {
+        if self.format == OutputFormat::Json {
+            return print_json(report);
+        }
+
+        if report.events.is_empty() {
+            println!("No migration has run yet");
+        }
+
+        report.events.iter().for_each(|record| {
+            let event = match record.event {
+                LedgerEvent::Up => self.paint("up  ", Style::new().green().bold()),
+                LedgerEvent::Down => self.paint("down", Style::new().yellow().bold()),
+            };
+            let name = self.paint(&record.name, Style::new().blue().bold());
+            let stamped = if record.details.stamped {
+                self.paint(" (stamped)", Style::new().dimmed())
+            } else {
+                String::new()
+            };
+            let operator = match (&record.details.user, &record.details.host) {
+                (Some(user), Some(host)) => format!("{user}@{host}"),
+                (user, host) => user.clone().or_else(|| host.clone()).unwrap_or_default(),
+            };
+
+            println!(
+                "{} | {} | {:<20} | {}{}",
+                record.recorded_at.to_rfc3339(),
+                event,
+                name,
+                operator,
+                stamped
+            );
+        });
+
+        Ok(())
+    }
+
+    fn status_details(&self, revision: &RevisionRecord) {
+        let Some(details) = &revision.details else {
+            return;
+        };
+        let field = |label: &str, value: Option<String>| {
+            if let Some(value) = value {
+                println!(
+                    "    {:<18} {}",
+                    self.paint(label, Style::new().dimmed()),
+                    value
+                );
+            }
+        };
+
+        field("revision", details.revision.clone());
+        field(
+            "down revision",
+            (!details.down_revision.is_empty()).then(|| details.down_revision.join(", ")),
+        );
+        field(
+            "applied at",
+            revision
+                .applied_at
+                .map(|applied_at| applied_at.to_rfc3339()),
+        );
+        field(
+            "duration",
+            details
+                .duration
+                .map(|duration| format!("{} ms", duration.as_millis())),
+        );
+        field("host", details.host.clone());
+        field("user", details.user.clone());
+        field("vectorctl version", details.vectorctl_version.clone());
+        field("migrator version", details.migrator_version.clone());
+        field("stamped", details.stamped.then(|| "yes".into()));
+        field("checksum", revision.checksum.clone());
+        field(
+            "record format",
+            revision.format_version.map(|version| version.to_string()),
+        );
+    }
+
+    /// Plain confirmation of a command without a report, omitted from JSON output.
+    pub fn message(&self, message: &str) {
+        if self.format == OutputFormat::Text {
+            println!("{message}");
+        }
+    }
+
+    fn paint(&self, text: &str, style: Style) -> String {
+        if self.use_colors {
+            text.style(style).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), MigrationError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use crate::{
+    MigrationTrait,
+    migrator::{Direction, MigrationError, MigrationStatus},
+};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+use uuid::Uuid;
+use vectorctl_backend::generic::{HistoryEntry, LedgerDetails, LedgerEvent};
+
+/// A revision as listed by `status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RevisionRecord {
+    /// migration name
+    pub name: String,
+    /// revision ID
+    pub revision: String,
+    /// parent revisions, more than one for a merge revision
+    pub parent: Vec<String>,
+    pub status: MigrationStatus,
+    /// when the migration was recorded in the ledger, `None` while pending
+    pub applied_at: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    /// checksum recorded in the ledger
+    pub checksum: Option<String>,
+    /// format version of the ledger record
+    pub format_version: Option<u32>,
+    /// audit details of the ledger record, `None` while pending
+    pub details: Option<LedgerDetails>,
+}
+
+/// Every revision in revision order, with its state in the ledger.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StatusReport {
+    pub revisions: Vec<RevisionRecord>,
+}
+
+/// A ledger event as listed by `history`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HistoryRecord {
+    /// ID of the ledger record
+    pub id: Uuid,
+    /// migration name
+    pub name: String,
+    pub event: LedgerEvent,
+    pub recorded_at: DateTime<Utc>,
+    /// checksum of the migration when the event was recorded
+    pub checksum: Option<String>,
+    /// format version of the ledger record
+    pub format_version: u32,
+    pub details: LedgerDetails,
+}
+
+impl From<HistoryEntry<Uuid>> for HistoryRecord {
+    fn from(entry: HistoryEntry<Uuid>) -> Self {
+        Self {
+            id: entry.id,
+            name: entry.name,
+            event: entry.event,
+            recorded_at: entry.recorded_at,
+            checksum: entry.checksum,
+            format_version: entry.format_version,
+            details: entry.details,
+        }
+    }
+}
+
+/// Every event of the ledger, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryReport {
+    pub events: Vec<HistoryRecord>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Applied,
+    RolledBack,
+    Failed,
+}
+
+/// A migration executed by a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepReport {
+    /// migration name
+    pub name: String,
+    /// revision ID
+    pub revision: String,
+    /// either [`Direction::Up`] or [`Direction::Down`]
+    pub direction: Direction,
+    pub outcome: Outcome,
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StepReport {
+    pub(crate) fn new(
+        migration: &dyn MigrationTrait,
+        direction: Direction,
+        duration: Duration,
+        result: &Result<(), MigrationError>,
+    ) -> Self {
+        let (outcome, error) = match (result, direction) {
+            (Err(err), _) => (Outcome::Failed, Some(err.to_string())),
+            (Ok(()), Direction::Down) => (Outcome::RolledBack, None),
+            (Ok(()), _) => (Outcome::Applied, None),
+        };
+        Self {
+            name: migration.name(),
+            revision: migration.revision().revision.to_owned(),
+            direction,
+            outcome,
+            duration,
+            error,
+        }
+    }
+}
+
+/// The migrations a run executed, in the order they completed. A failed
+/// migration is listed with its error, the ones that never started are not.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RunReport {
+    pub steps: Vec<StepReport>,
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_run_report_json_shape() {
+        let report = RunReport {
+            steps: vec![
+                StepReport {
+                    name: "version_a".into(),
+                    revision: "a".into(),
+                    direction: Direction::Down,
+                    outcome: Outcome::RolledBack,
+                    duration: Duration::from_millis(1500),
+                    error: None,
+                },
+                StepReport {
+                    name: "version_b".into(),
+                    revision: "b".into(),
+                    direction: Direction::Up,
+                    outcome: Outcome::Failed,
+                    duration: Duration::from_millis(3),
+                    error: Some("Other boom".into()),
+                },
+            ],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "steps": [
+                    {
+                        "name": "version_a",
+                        "revision": "a",
+                        "direction": "down",
+                        "outcome": "rolled_back",
+                        "duration_ms": 1500,
+                    },
+                    {
+                        "name": "version_b",
+                        "revision": "b",
+                        "direction": "up",
+                        "outcome": "failed",
+                        "duration_ms": 3,
+                        "error": "Other boom",
+                    },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_status_report_json_shape() {
+        let report = StatusReport {
+            revisions: vec![RevisionRecord {
+                name: "version_b".into(),
+                revision: "b".into(),
+                parent: vec!["a".into()],
+                status: MigrationStatus::Pending,
+                applied_at: None,
+                message: Some("add index".into()),
+                checksum: None,
+                format_version: None,
+                details: None,
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "revisions": [{
+                    "name": "version_b",
+                    "revision": "b",
+                    "parent": ["a"],
+                    "status": "pending",
+                    "applied_at": null,
+                    "message": "add index",
+                    "checksum": null,
+                    "format_version": null,
+                    "details": null,
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_history_report_json_shape() {
+        let report = HistoryReport {
+            events: vec![HistoryRecord {
+                id: Uuid::nil(),
+                name: "version_a".into(),
+                event: LedgerEvent::Down,
+                recorded_at: DateTime::from_timestamp(1_747_925_950, 0).unwrap(),
+                checksum: None,
+                format_version: 2,
+                details: LedgerDetails {
+                    revision: Some("a".into()),
+                    user: Some("deploy".into()),
+                    ..Default::default()
+                },
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "events": [{
+                    "id": "00000000-0000-0000-0000-000000000000",
+                    "name": "version_a",
+                    "event": "down",
+                    "recorded_at": "2025-05-22T14:59:10Z",
+                    "checksum": null,
+                    "format_version": 2,
+                    "details": {
+                        "revision": "a",
+                        "user": "deploy",
+                    },
+                }]
+            })
+        );
+    }
+}
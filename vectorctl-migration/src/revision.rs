@@ -1,7 +1,6 @@
 use crate::{MigrationTrait, migrator::Migration};
-use rustc_hash::FxHashMap as HashMap;
-use std::sync::Arc;
-use tinyvec::ArrayVec;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use std::{cmp::Reverse, collections::BinaryHeap, ffi::OsStr, path::Path, sync::Arc};
 use uuid::Uuid;
 
 type Revision = Arc<str>;
@@ -11,6 +10,25 @@ type Ix = usize;
 pub enum RevisionGraphError {
     #[error("{0} not found")]
     NotFound(String),
+    #[error("unknown target revision `{0}`")]
+    UnknownTarget(String),
+    #[error("target `{target}` is ambiguous, it matches {}", .candidates.join(", "))]
+    AmbiguousTarget {
+        target: String,
+        candidates: Vec<String>,
+    },
+    #[error("target `{0}` goes past the first or the last revision")]
+    TargetOutOfRange(String),
+    #[error("target `{0}` is not applied, there is nothing to roll back to")]
+    NotApplied(String),
+    #[error("revision cycle through {}", .0.join(", "))]
+    Cycle(Vec<String>),
+    #[error("revision `{revision}` has unknown down_revision `{parent}`")]
+    DanglingParent { revision: String, parent: String },
+    #[error("revision ID `{0}` is defined more than once")]
+    DuplicateRevision(String),
+    #[error("multiple root revisions ({}), only one may have no down_revision", .0.join(", "))]
+    MultipleRoots(Vec<String>),
 }
 
 #[derive(Debug)]
@@ -18,11 +36,19 @@ pub struct Node {
     /// revision ID
     pub revision: Revision,
     /// child indices
-    pub children: ArrayVec<[Ix; 4]>,
+    pub children: Vec<Ix>,
     /// migration for this revision
     pub migration: Migration,
-    /// parent index
-    pub parent: Option<Ix>,
+    /// parent indices, more than one for a merge revision
+    pub parents: Vec<Ix>,
+}
+
+/// A target resolved by [`RevisionGraph::resolve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<'a> {
+    /// before the first revision, nothing applied
+    Base,
+    Revision(&'a str),
 }
 
 #[derive(Debug)]
@@ -30,9 +56,11 @@ pub struct RevisionGraph {
     nodes: Vec<Node>,
     index: HashMap<Revision, Ix>,
     /// index of the first revision
-    head_ix: Ix,
-    /// index of the latest revision
-    queue_ix: Ix,
+    root_ix: Ix,
+    /// indices of the latest revisions, one per unmerged branch
+    head_ixs: Vec<Ix>,
+    /// every index, parents before children
+    order: Vec<Ix>,
 }
 
 impl RevisionGraph {
@@ -40,87 +68,287 @@ impl RevisionGraph {
         let capacity = migrations.len();
         let mut nodes = Vec::with_capacity(capacity);
         let mut index = HashMap::with_capacity_and_hasher(capacity, Default::default());
-        migrations
-            .into_iter()
-            .enumerate()
-            .for_each(|(ix, migration)| {
-                let meta = migration.runner.revision();
-                let revision: Revision = Arc::from(meta.revision);
-                index.insert(revision.clone(), ix);
-                nodes.push(Node {
-                    revision,
-                    migration,
-                    parent: None,
-                    children: ArrayVec::new(),
-                });
-            });
-        (0..nodes.len()).for_each(|ix| {
-            if let Some(parent_rev) = nodes[ix].migration.runner.revision().down_revision
-                && let Some(&p_ix) = index.get(parent_rev)
-            {
-                nodes[ix].parent = Some(p_ix);
-                nodes[p_ix].children.push(ix);
+        for (ix, migration) in migrations.into_iter().enumerate() {
+            let meta = migration.runner.revision();
+            let revision: Revision = Arc::from(meta.revision);
+            if index.insert(revision.clone(), ix).is_some() {
+                return Err(RevisionGraphError::DuplicateRevision(revision.to_string()));
             }
-        });
-        let queue_ix = nodes
-            .iter()
-            .position(|Node { children, .. }| children.is_empty())
-            .ok_or_else(|| RevisionGraphError::NotFound("queue".into()))?;
+            nodes.push(Node {
+                revision,
+                migration,
+                parents: Vec::new(),
+                children: Vec::new(),
+            });
+        }
+        for ix in 0..nodes.len() {
+            let parents = nodes[ix]
+                .migration
+                .runner
+                .revision()
+                .down_revision
+                .iter()
+                .map(|parent_rev| {
+                    index.get(*parent_rev).copied().ok_or_else(|| {
+                        RevisionGraphError::DanglingParent {
+                            revision: nodes[ix].revision.to_string(),
+                            parent: parent_rev.to_string(),
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            parents
+                .iter()
+                .for_each(|&p_ix| nodes[p_ix].children.push(ix));
+            nodes[ix].parents = parents;
+        }
+
+        let (root_ix, order) = Self::validate(&nodes)?;
 
-        let head_ix = nodes
+        let head_ixs = order
             .iter()
-            .position(|Node { parent, .. }| parent.is_none())
-            .ok_or_else(|| RevisionGraphError::NotFound("head".into()))?;
+            .copied()
+            .filter(|&ix| nodes[ix].children.is_empty())
+            .collect::<Vec<_>>();
 
         Ok(Self {
             nodes,
             index,
-            head_ix,
-            queue_ix,
+            root_ix,
+            head_ixs,
+            order,
         })
     }
 
+    /// Checks that the linked nodes form a single-rooted acyclic graph and
+    /// returns its root along with a topological order.
+    fn validate(nodes: &[Node]) -> Result<(Ix, Vec<Ix>), RevisionGraphError> {
+        let order = Self::topological_order(nodes);
+        if order.len() < nodes.len() {
+            let ordered = order.iter().copied().collect::<HashSet<_>>();
+            let mut cycle = (0..nodes.len())
+                .filter(|ix| !ordered.contains(ix))
+                .map(|ix| nodes[ix].revision.to_string())
+                .collect::<Vec<_>>();
+            cycle.sort();
+            return Err(RevisionGraphError::Cycle(cycle));
+        }
+
+        let roots = order
+            .iter()
+            .copied()
+            .filter(|&ix| nodes[ix].parents.is_empty())
+            .collect::<Vec<_>>();
+        match roots.as_slice() {
+            [] => Err(RevisionGraphError::NotFound("root".into())),
+            [root_ix] => Ok((*root_ix, order)),
+            _ => Err(RevisionGraphError::MultipleRoots(
+                roots
+                    .iter()
+                    .map(|&ix| nodes[ix].revision.to_string())
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Kahn's algorithm, picking the oldest ready revision first so that the
+    /// order does not depend on how `migrations()` lists them. Revisions on or
+    /// behind a cycle are left out.
+    fn topological_order(nodes: &[Node]) -> Vec<Ix> {
+        let key = |ix: Ix| {
+            let date = nodes[ix].migration.runner.revision().date.to_owned();
+            Reverse((date, nodes[ix].revision.clone(), ix))
+        };
+        let mut in_degree = nodes
+            .iter()
+            .map(|Node { parents, .. }| parents.len())
+            .collect::<Vec<_>>();
+        let mut ready = (0..nodes.len())
+            .filter(|&ix| in_degree[ix] == 0)
+            .map(key)
+            .collect::<BinaryHeap<_>>();
+
+        let mut order = Vec::with_capacity(nodes.len());
+        while let Some(Reverse((_, _, ix))) = ready.pop() {
+            order.push(ix);
+            nodes[ix].children.iter().for_each(|&child| {
+                in_degree[child] -= 1;
+                if in_degree[child] == 0 {
+                    ready.push(key(child));
+                }
+            });
+        }
+
+        order
+    }
+
     fn ix(&self, rev: &str) -> Option<Ix> {
         self.index.get(rev).copied()
     }
 
-    fn child_ix(&self, ix: Ix) -> Option<Ix> {
-        self.nodes[ix].children.first().copied()
+    fn target_ix(&self, rev: &str) -> Result<Ix, RevisionGraphError> {
+        self.ix(rev)
+            .ok_or_else(|| RevisionGraphError::UnknownTarget(rev.to_owned()))
     }
 
-    fn parent_ix(&self, ix: Ix) -> Option<Ix> {
-        self.nodes[ix].parent
+    /// `ix` and every revision it descends from.
+    fn ancestors(&self, ix: Ix) -> HashSet<Ix> {
+        let mut seen = HashSet::default();
+        let mut stack = vec![ix];
+        while let Some(ix) = stack.pop() {
+            if seen.insert(ix) {
+                stack.extend(&self.nodes[ix].parents);
+            }
+        }
+        seen
     }
 
-    pub fn head(&self) -> &str {
-        self.nodes[self.head_ix].revision.as_ref()
+    /// Resolves a target given on the command line, trying in turn:
+    /// - `head`, the latest revision, as long as there is a single one
+    /// - `base`, before the first revision
+    /// - `+N` and `-N`, N revisions after or before the applied ones
+    /// - a revision ID, a migration name or the path of its file
+    /// - a prefix of a single revision ID
+    pub fn resolve(&self, target: &str) -> Result<Target<'_>, RevisionGraphError> {
+        let ix = match target {
+            "base" => None,
+            "head" => Some(
+                self.unique(target, &self.head_ixs)?
+                    .ok_or_else(|| RevisionGraphError::UnknownTarget(target.to_owned()))?,
+            ),
+            _ => match parse_relative(target) {
+                Some(steps) => self.relative(target, steps)?,
+                None => Some(self.lookup(target)?),
+            },
+        };
+        Ok(match ix {
+            None => Target::Base,
+            Some(ix) => Target::Revision(self.nodes[ix].revision.as_ref()),
+        })
     }
 
-    pub fn queue(&self) -> &str {
-        self.nodes[self.queue_ix].revision.as_ref()
+    /// The only index of `ixs`, if any.
+    fn unique(&self, target: &str, ixs: &[Ix]) -> Result<Option<Ix>, RevisionGraphError> {
+        match ixs {
+            [] => Ok(None),
+            [ix] => Ok(Some(*ix)),
+            _ => Err(RevisionGraphError::AmbiguousTarget {
+                target: target.to_owned(),
+                candidates: ixs
+                    .iter()
+                    .map(|&ix| self.nodes[ix].revision.to_string())
+                    .collect(),
+            }),
+        }
     }
 
-    pub fn forward_path(&self, current: Option<&str>, target: &str) -> Vec<&Node> {
-        let start_ix = current.and_then(|rev| self.ix(rev));
-        let target_ix = self.ix(target).expect("target revision must exist");
+    fn lookup(&self, target: &str) -> Result<Ix, RevisionGraphError> {
+        if let Some(ix) = self.ix(target) {
+            return Ok(ix);
+        }
 
-        std::iter::successors(start_ix, |&ix| self.child_ix(ix))
-            .take_while(|&ix| ix != target_ix)
-            .chain(std::iter::once(target_ix))
-            .map(|ix| &self.nodes[ix])
-            .collect()
+        let stem = Path::new(target)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or(target);
+        if let Some(ix) = self
+            .order
+            .iter()
+            .copied()
+            .find(|&ix| self.nodes[ix].migration.runner.name() == stem)
+        {
+            return Ok(ix);
+        }
+
+        let matches = self
+            .order
+            .iter()
+            .copied()
+            .filter(|&ix| self.nodes[ix].revision.starts_with(target))
+            .collect::<Vec<_>>();
+        self.unique(target, &matches)?
+            .ok_or_else(|| RevisionGraphError::UnknownTarget(target.to_owned()))
+    }
+
+    /// Walks `steps` revisions from the latest applied one, towards children
+    /// when positive and towards parents when negative. `None` is the base.
+    fn relative(&self, target: &str, steps: isize) -> Result<Option<Ix>, RevisionGraphError> {
+        let applied = |ix: Ix| self.nodes[ix].migration.status.is_applied();
+        let current = self
+            .order
+            .iter()
+            .copied()
+            .filter(|&ix| applied(ix) && !self.nodes[ix].children.iter().any(|&c| applied(c)))
+            .collect::<Vec<_>>();
+        let out_of_range = || RevisionGraphError::TargetOutOfRange(target.to_owned());
+
+        let mut position = self.unique(target, &current)?;
+        for _ in 0..steps.unsigned_abs() {
+            position = match (position, steps > 0) {
+                (None, true) => Some(self.root_ix),
+                (None, false) => return Err(out_of_range()),
+                (Some(ix), true) => Some(
+                    self.unique(target, &self.nodes[ix].children)?
+                        .ok_or_else(out_of_range)?,
+                ),
+                (Some(ix), false) => self.unique(target, &self.nodes[ix].parents)?,
+            };
+        }
+        Ok(position)
     }
 
-    pub fn backward_path(&self, current: Option<&str>, stop: Option<&str>) -> Vec<&Node> {
-        let stop_ix = stop.and_then(|r| self.ix(r));
-        let start_ix = current.and_then(|r| self.ix(r));
+    /// The first revision, the one every other descends from.
+    pub fn root(&self) -> &str {
+        self.nodes[self.root_ix].revision.as_ref()
+    }
 
-        std::iter::successors(start_ix, |&ix| self.parent_ix(ix))
-            .take_while(|&ix| Some(ix) != stop_ix)
-            .map(|ix| &self.nodes[ix])
+    /// The latest revisions, one per unmerged branch. `head` resolves to the
+    /// only one.
+    pub fn heads(&self) -> Vec<&str> {
+        self.head_ixs
+            .iter()
+            .map(|&ix| self.nodes[ix].revision.as_ref())
             .collect()
     }
 
+    /// Revisions to apply to reach `target`, or every head when `None`,
+    /// parents before children.
+    pub fn forward_path(&self, target: Option<&str>) -> Result<Vec<&Node>, RevisionGraphError> {
+        let keep = target
+            .map(|rev| self.target_ix(rev).map(|ix| self.ancestors(ix)))
+            .transpose()?;
+
+        Ok(self
+            .order
+            .iter()
+            .filter(|ix| keep.as_ref().is_none_or(|keep| keep.contains(ix)))
+            .map(|&ix| &self.nodes[ix])
+            .collect())
+    }
+
+    /// Revisions to roll back so that only `stop` and its ancestors remain, or
+    /// every revision when `None`, children before parents. The path covers
+    /// every defined revision, whether the ledger holds it or not, callers
+    /// keep the applied ones.
+    pub fn backward_path(&self, stop: Option<&str>) -> Result<Vec<&Node>, RevisionGraphError> {
+        let keep = stop
+            .map(|rev| self.target_ix(rev).map(|ix| self.ancestors(ix)))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(self
+            .order
+            .iter()
+            .rev()
+            .filter(|ix| !keep.contains(ix))
+            .map(|&ix| &self.nodes[ix])
+            .collect())
+    }
+
+    pub fn node(&self, rev: &str) -> Option<&Node> {
+        self.ix(rev).map(|ix| &self.nodes[ix])
+    }
+
     pub fn get(&self, rev: &str) -> Option<(Option<Uuid>, &dyn MigrationTrait)> {
         self.ix(rev).map(|ix| {
             let Node { migration, .. } = &self.nodes[ix];
@@ -129,74 +357,51 @@ impl RevisionGraph {
     }
 }
 
+/// Reads `+N` and `-N` as a signed number of steps.
+fn parse_relative(target: &str) -> Option<isize> {
+    let (sign, steps) = match target.split_at_checked(1)? {
+        ("+", steps) => (1, steps),
+        ("-", steps) => (-1, steps),
+        _ => return None,
+    };
+    Some(sign * steps.parse::<isize>().ok().filter(|steps| *steps >= 0)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::MigrationError;
-    use crate::MigrationMeta;
-    use crate::Revision as RevisionMeta;
     use crate::migrator::MigrationStatus;
+    use crate::test_support::TestMigration;
 
     use uuid::Uuid;
 
-    #[derive(Debug)]
-    struct TestMigration {
-        rev: &'static str,
-        down_rev: Option<&'static str>,
-    }
-
-    impl MigrationMeta for TestMigration {
-        fn name(&self) -> String {
-            self.rev.to_string()
-        }
-
-        fn revision(&self) -> RevisionMeta<'_> {
-            RevisionMeta {
-                message: None,
-                revision: self.rev,
-                down_revision: self.down_rev,
-                date: "2023-01-01",
-            }
-        }
-    }
-
-    #[async_trait::async_trait]
-    impl MigrationTrait for TestMigration {
-        async fn up(&self, _ctx: &crate::context::Context) -> Result<(), MigrationError> {
-            Ok(())
-        }
-
-        async fn down(&self, _ctx: &crate::context::Context) -> Result<(), MigrationError> {
-            Ok(())
-        }
-    }
-
     fn make_migration(
         rev: &'static str,
-        down_rev: Option<&'static str>,
+        down_rev: &'static [&'static str],
         status: Option<MigrationStatus>,
     ) -> Migration {
         Migration {
             id: Some(Uuid::now_v7()),
             status: status.unwrap_or(MigrationStatus::Pending),
-            runner: Box::new(TestMigration { rev, down_rev }),
+            entry: None,
+            runner: Box::new(TestMigration::new(rev, down_rev)),
         }
     }
 
     #[test]
     fn test_graph_construction_and_paths() {
         let migrations = vec![
-            make_migration("a", None, None),
-            make_migration("b", Some("a"), None),
-            make_migration("c", Some("b"), None),
+            make_migration("a", &[], None),
+            make_migration("b", &["a"], None),
+            make_migration("c", &["b"], None),
         ];
 
         let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
 
-        assert_eq!(graph.queue(), "c");
-        assert_eq!(graph.head(), "a");
+        assert_eq!(graph.heads(), vec!["c"]);
+        assert_eq!(graph.root(), "a");
 
-        let forward = graph.forward_path(Some("a"), "c");
+        let forward = graph.forward_path(Some("c")).unwrap();
         assert_eq!(
             forward
                 .iter()
@@ -205,7 +410,7 @@ mod tests {
             vec!["a", "b", "c"]
         );
 
-        let backward = graph.backward_path(Some("c"), Some("a"));
+        let backward = graph.backward_path(Some("a")).unwrap();
         assert_eq!(
             backward
                 .iter()
@@ -221,14 +426,14 @@ mod tests {
 
     #[test]
     fn test_graph_contruction_with_applied_revision() {
-        let migrations = vec![make_migration("a", None, Some(MigrationStatus::Applied))];
+        let migrations = vec![make_migration("a", &[], Some(MigrationStatus::Applied))];
 
         let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
 
-        assert_eq!(graph.head(), "a");
-        assert_eq!(graph.queue(), "a");
+        assert_eq!(graph.root(), "a");
+        assert_eq!(graph.heads(), vec!["a"]);
 
-        let forward = graph.forward_path(None, "a");
+        let forward = graph.forward_path(Some("a")).unwrap();
         assert_eq!(
             forward
                 .iter()
@@ -237,7 +442,7 @@ mod tests {
             vec!["a"]
         );
 
-        let backward = graph.backward_path(Some("a"), None);
+        let backward = graph.backward_path(None).unwrap();
         assert_eq!(
             backward
                 .iter()
@@ -250,12 +455,228 @@ mod tests {
     #[test]
     fn test_no_head_error() {
         let migrations = vec![
-            make_migration("a", Some("c"), None),
-            make_migration("b", Some("a"), None),
-            make_migration("c", Some("b"), None),
+            make_migration("a", &["c"], None),
+            make_migration("b", &["a"], None),
+            make_migration("c", &["b"], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations);
+        assert!(matches!(graph, Err(RevisionGraphError::Cycle(cycle)) if cycle == ["a", "b", "c"]));
+    }
+
+    #[test]
+    fn test_cycle_behind_root_error() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("b", &["a", "c"], None),
+            make_migration("c", &["b"], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations);
+        assert!(matches!(graph, Err(RevisionGraphError::Cycle(cycle)) if cycle == ["b", "c"]));
+    }
+
+    #[test]
+    fn test_dangling_parent_error() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("b", &["typo"], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations);
+        assert!(matches!(
+            graph,
+            Err(RevisionGraphError::DanglingParent { revision, parent })
+                if revision == "b" && parent == "typo"
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_revision_error() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("a", &[], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations);
+        assert!(matches!(graph, Err(RevisionGraphError::DuplicateRevision(rev)) if rev == "a"));
+    }
+
+    #[test]
+    fn test_multiple_roots_error() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("b", &[], None),
         ];
 
         let graph = RevisionGraph::try_from(migrations);
-        assert!(matches!(graph, Err(RevisionGraphError::NotFound(_))));
+        assert!(
+            matches!(graph, Err(RevisionGraphError::MultipleRoots(roots)) if roots == ["a", "b"])
+        );
+    }
+
+    #[test]
+    fn test_unknown_target_error() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("b", &["a"], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
+        assert!(matches!(
+            graph.forward_path(Some("typo")),
+            Err(RevisionGraphError::UnknownTarget(rev)) if rev == "typo"
+        ));
+        assert!(matches!(
+            graph.backward_path(Some("typo")),
+            Err(RevisionGraphError::UnknownTarget(rev)) if rev == "typo"
+        ));
+    }
+
+    fn revisions(path: Vec<&Node>) -> Vec<&str> {
+        path.into_iter()
+            .map(|node| node.revision.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn test_branches_are_all_heads() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("b", &["a"], None),
+            make_migration("c", &["a"], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
+
+        assert_eq!(graph.root(), "a");
+        assert_eq!(graph.heads(), vec!["b", "c"]);
+        assert_eq!(
+            revisions(graph.forward_path(None).unwrap()),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            revisions(graph.forward_path(Some("c")).unwrap()),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            revisions(graph.backward_path(None).unwrap()),
+            vec!["c", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn test_merge_revision_joins_branches() {
+        let migrations = vec![
+            make_migration("d", &["b", "c"], None),
+            make_migration("c", &["a"], None),
+            make_migration("b", &["a"], None),
+            make_migration("a", &[], None),
+        ];
+
+        let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
+
+        assert_eq!(graph.heads(), vec!["d"]);
+        assert_eq!(
+            revisions(graph.forward_path(Some("d")).unwrap()),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            revisions(graph.forward_path(Some("b")).unwrap()),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            revisions(graph.backward_path(Some("b")).unwrap()),
+            vec!["d", "c"]
+        );
+        assert_eq!(
+            revisions(graph.backward_path(Some("a")).unwrap()),
+            vec!["d", "c", "b"]
+        );
+    }
+
+    fn resolve<'a>(graph: &'a RevisionGraph, target: &str) -> Target<'a> {
+        graph.resolve(target).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_symbolic_targets() {
+        let applied = Some(MigrationStatus::Applied);
+        let migrations = vec![
+            make_migration("3f2a", &[], applied.clone()),
+            make_migration("3f9c", &["3f2a"], applied),
+            make_migration("7b1d", &["3f9c"], None),
+            make_migration("8e04", &["7b1d"], None),
+        ];
+        let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
+
+        assert_eq!(resolve(&graph, "head"), Target::Revision("8e04"));
+        assert_eq!(resolve(&graph, "base"), Target::Base);
+        assert_eq!(resolve(&graph, "3f9c"), Target::Revision("3f9c"));
+        assert_eq!(resolve(&graph, "7b"), Target::Revision("7b1d"));
+        assert_eq!(resolve(&graph, "version_7b1d"), Target::Revision("7b1d"));
+        assert_eq!(
+            resolve(&graph, "src/version_8e04.rs"),
+            Target::Revision("8e04")
+        );
+
+        assert_eq!(resolve(&graph, "+0"), Target::Revision("3f9c"));
+        assert_eq!(resolve(&graph, "+2"), Target::Revision("8e04"));
+        assert_eq!(resolve(&graph, "-1"), Target::Revision("3f2a"));
+        assert_eq!(resolve(&graph, "-2"), Target::Base);
+
+        assert!(matches!(
+            graph.resolve("3f"),
+            Err(RevisionGraphError::AmbiguousTarget { candidates, .. })
+                if candidates == ["3f2a", "3f9c"]
+        ));
+        assert!(matches!(
+            graph.resolve("+3"),
+            Err(RevisionGraphError::TargetOutOfRange(target)) if target == "+3"
+        ));
+        assert!(matches!(
+            graph.resolve("-3"),
+            Err(RevisionGraphError::TargetOutOfRange(_))
+        ));
+        assert!(matches!(
+            graph.resolve("typo"),
+            Err(RevisionGraphError::UnknownTarget(target)) if target == "typo"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_relative_from_base_and_across_branches() {
+        let migrations = vec![
+            make_migration("a", &[], None),
+            make_migration("b", &["a"], None),
+            make_migration("c", &["a"], None),
+        ];
+        let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
+
+        assert_eq!(resolve(&graph, "+1"), Target::Revision("a"));
+        assert!(matches!(
+            graph.resolve("+2"),
+            Err(RevisionGraphError::AmbiguousTarget { candidates, .. }) if candidates == ["b", "c"]
+        ));
+        assert!(matches!(
+            graph.resolve("head"),
+            Err(RevisionGraphError::AmbiguousTarget { candidates, .. }) if candidates == ["b", "c"]
+        ));
+    }
+
+    #[test]
+    fn test_many_children() {
+        let migrations = std::iter::once(make_migration("a", &[], None))
+            .chain(
+                ["b", "c", "d", "e", "f", "g"]
+                    .into_iter()
+                    .map(|rev| make_migration(rev, &["a"], None)),
+            )
+            .collect();
+
+        let graph = RevisionGraph::try_from(migrations).expect("graph should be created");
+
+        assert_eq!(graph.heads().len(), 6);
+        assert_eq!(graph.forward_path(None).unwrap().len(), 7);
     }
 }
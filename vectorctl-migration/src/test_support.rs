@@ -0,0 +1,85 @@
+//! Fixtures shared by the unit tests.
+
+use std::time::Duration;
+
+use crate::{MigrationError, MigrationMeta, MigrationTrait, Revision, context::Context};
+
+/// A migration named `version_{rev}` that does nothing, or fails to run `up`
+/// when built with [`TestMigration::failing`]. Its `up` lasts as long as
+/// given to [`TestMigration::taking`].
+#[derive(Debug)]
+pub struct TestMigration {
+    pub rev: &'static str,
+    pub down_rev: &'static [&'static str],
+    pub independent: bool,
+    pub fails: bool,
+    pub duration: Duration,
+}
+
+impl TestMigration {
+    pub fn new(rev: &'static str, down_rev: &'static [&'static str]) -> Self {
+        Self {
+            rev,
+            down_rev,
+            independent: false,
+            fails: false,
+            duration: Duration::ZERO,
+        }
+    }
+
+    pub fn independent(self) -> Self {
+        Self {
+            independent: true,
+            ..self
+        }
+    }
+
+    pub fn failing(self) -> Self {
+        Self {
+            fails: true,
+            ..self
+        }
+    }
+
+    pub fn taking(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+}
+
+impl MigrationMeta for TestMigration {
+    fn name(&self) -> String {
+        format!("version_{}", self.rev)
+    }
+
+    fn revision(&self) -> Revision<'_> {
+        Revision {
+            message: None,
+            revision: self.rev,
+            down_revision: self.down_rev,
+            date: "2023-01-01",
+        }
+    }
+
+    fn checksum(&self) -> Option<String> {
+        Some(format!("{}-checksum", self.rev))
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for TestMigration {
+    async fn up(&self, _ctx: &Context) -> Result<(), MigrationError> {
+        tokio::time::sleep(self.duration).await;
+        if self.fails {
+            return Err(MigrationError::Other(format!("{} failed", self.rev)));
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _ctx: &Context) -> Result<(), MigrationError> {
+        Ok(())
+    }
+
+    fn independent(&self) -> bool {
+        self.independent
+    }
+}
@@ -7,3 +7,4 @@ pub use handlebars;
 
 pub use handlebars::RenderError;
 pub use migration::MigrationTemplate;
+pub use registry::Registry;
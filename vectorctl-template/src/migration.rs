@@ -5,7 +5,7 @@ use handlebars::{RenderError, RenderErrorReason};
 use lazy_regex::{Lazy, Regex, lazy_regex};
 use serde::Serialize;
 
-use crate::registry::REGISTRY;
+use crate::registry::{REGISTRY, Registry};
 
 static SEMVER_REGEX: Lazy<Regex> = lazy_regex!(
     r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-((?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*))*))?(?:\+([0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*))?$"
@@ -13,6 +13,11 @@ static SEMVER_REGEX: Lazy<Regex> = lazy_regex!(
 static STRING_REGEX: Lazy<Regex> = lazy_regex!(r"^[a-zA-Z0-9_]+$");
 
 const DEFAULT_RUST_EDITION: &str = "2021";
+/// Release the scaffold pins vectorctl at, as `tag = "v{version}"`. Between
+/// releases this is still the version of the last release, whose tag may lack
+/// the APIs the templates use: a scaffold rendered by an unreleased build only
+/// builds once `cog bump` has set the version and pushed its tag, or with the
+/// dependency pointed at a checkout.
 const DEFAULT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const DEFAULT_PACKAGE_NAME: &str = "migration";
 
@@ -33,7 +38,15 @@ impl MigrationTemplate {
     }
 
     pub fn render(&self, out: impl AsRef<Path>) -> Result<(), RenderError> {
-        REGISTRY.render_all("migration", self, out)
+        self.render_with(&REGISTRY, out)
+    }
+
+    pub fn render_with(
+        &self,
+        registry: &Registry,
+        out: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
+        registry.render_all("migration", self, out)
     }
 }
 
@@ -61,8 +74,16 @@ impl MigrationTemplateBuilder {
     }
 
     pub fn render(&self, out: impl AsRef<Path>) -> Result<(), RenderError> {
+        self.render_with(&REGISTRY, out)
+    }
+
+    pub fn render_with(
+        &self,
+        registry: &Registry,
+        out: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
         self.build()
             .map_err(|err| RenderErrorReason::Other(err.to_string()))?
-            .render(out)
+            .render_with(registry, out)
     }
 }
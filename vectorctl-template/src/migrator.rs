@@ -4,7 +4,7 @@ use derive_builder::Builder;
 use handlebars::{RenderError, RenderErrorReason};
 use serde::Serialize;
 
-use crate::registry::REGISTRY;
+use crate::registry::{REGISTRY, Registry};
 
 #[derive(Builder, Serialize, Debug)]
 #[builder(build_fn(validate = "Self::validate"))]
@@ -19,7 +19,15 @@ impl MigratorTemplate {
     }
 
     pub fn render(&self, out: impl AsRef<Path>) -> Result<(), RenderError> {
-        REGISTRY.render_all("migrator", self, out)
+        self.render_with(&REGISTRY, out)
+    }
+
+    pub fn render_with(
+        &self,
+        registry: &Registry,
+        out: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
+        registry.render_all("migrator", self, out)
     }
 }
 
@@ -37,8 +45,16 @@ impl MigratorTemplateBuilder {
     }
 
     pub fn render(&self, out: impl AsRef<Path>) -> Result<(), RenderError> {
+        self.render_with(&REGISTRY, out)
+    }
+
+    pub fn render_with(
+        &self,
+        registry: &Registry,
+        out: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
         self.build()
             .map_err(|err| RenderErrorReason::Other(err.to_string()))?
-            .render(out)
+            .render_with(registry, out)
     }
 }
@@ -1,6 +1,6 @@
 use handlebars::{
-    Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError,
-    RenderErrorReason,
+    Context, DirectorySourceOptions, Handlebars, Helper, HelperDef, HelperResult, Output,
+    RenderContext, RenderError, RenderErrorReason,
 };
 use once_cell::sync::Lazy;
 use rayon::prelude::*;
@@ -80,6 +80,28 @@ impl Registry {
         Self { handlebars }
     }
 
+    /// Layers the `.hbs` files found under `dir` over the embedded templates.
+    /// A file replaces the embedded template at the same relative path, such as
+    /// `revision/{{filename}}.rs.hbs`, any other one is rendered next to them.
+    pub fn with_template_dir(mut self, dir: impl AsRef<Path>) -> Result<Self, RenderError> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("template directory `{}` not found", dir.display()),
+            )
+            .into());
+        }
+        self.handlebars
+            .register_templates_directory(dir, DirectorySourceOptions::default())?;
+        Ok(self)
+    }
+
+    /// Makes `helper` available to every template as `name`.
+    pub fn register_helper(&mut self, name: &str, helper: impl HelperDef + Send + Sync + 'static) {
+        self.handlebars.register_helper(name, Box::new(helper));
+    }
+
     fn write_bytes(path: &Path, bytes: &[u8]) -> io::Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
@@ -5,7 +5,7 @@ use handlebars::{RenderError, RenderErrorReason};
 use lazy_regex::{Lazy, Regex, lazy_regex};
 use serde::Serialize;
 
-use crate::registry::REGISTRY;
+use crate::registry::{REGISTRY, Registry};
 
 static FILENAME_REGEX: Lazy<Regex> = lazy_regex!(r"^[a-zA-Z][a-zA-Z0-9_]*$");
 static ISO_DATE_REGEX: Lazy<Regex> = lazy_regex!(r"^\d{4}-\d{2}-\d{2}(?:[ T]\d{2}:\d{2}:\d{2})?$");
@@ -22,8 +22,8 @@ pub struct RevisionTemplate {
     date: String,
     #[builder(setter(into))]
     revision_id: String,
-    #[builder(setter(into, strip_option), default)]
-    down_revision_id: Option<String>,
+    #[builder(setter(into), default)]
+    down_revision_ids: Vec<String>,
 }
 
 impl RevisionTemplate {
@@ -32,7 +32,15 @@ impl RevisionTemplate {
     }
 
     pub fn render(&self, out: impl AsRef<Path>) -> Result<(), RenderError> {
-        REGISTRY.render_all_with_filename_templates("revision", self, out)
+        self.render_with(&REGISTRY, out)
+    }
+
+    pub fn render_with(
+        &self,
+        registry: &Registry,
+        out: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
+        registry.render_all_with_filename_templates("revision", self, out)
     }
 }
 
@@ -72,8 +80,11 @@ impl RevisionTemplateBuilder {
             return Err("revision_id is required".into());
         }
 
-        if let Some(Some(down_revision_id)) = self.down_revision_id.as_ref()
-            && !REVISION_ID_REGEX.is_match(down_revision_id)
+        if let Some(down_revision_id) = self
+            .down_revision_ids
+            .iter()
+            .flatten()
+            .find(|down_revision_id| !REVISION_ID_REGEX.is_match(down_revision_id))
         {
             return Err(format!(
                 "down_revision_id `{}` may only contain letters, numbers, dashes or underscores",
@@ -91,8 +102,16 @@ impl RevisionTemplateBuilder {
     }
 
     pub fn render(&self, out: impl AsRef<Path>) -> Result<(), RenderError> {
+        self.render_with(&REGISTRY, out)
+    }
+
+    pub fn render_with(
+        &self,
+        registry: &Registry,
+        out: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
         self.build()
             .map_err(|err| RenderErrorReason::Other(err.to_string()))?
-            .render(out)
+            .render_with(registry, out)
     }
 }